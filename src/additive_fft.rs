@@ -0,0 +1,498 @@
+use codec::{frame, unframe, validate_shards, DecodeError};
+use config::{CodecConfig, ConfigError};
+use gf216::GF216;
+use gf232::GF232;
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Mul};
+
+/// A Galois field of characteristic 2, the elements of which are binary polynomials of
+/// degree less than `BITS`. The powers of `x` are the basis `v_i` of the subspaces used
+/// as evaluation points, so that the point `omega_j = sum(j_i * v_i)` is the element
+/// with the bits of `j`.
+pub trait BinaryField:
+    Copy + Eq + Debug + Add<Output = Self> + AddAssign + Mul<Output = Self> + Div<Output = Self>
+{
+    const BITS: usize;
+
+    /// Returns the element with the bits of the given index
+    fn from_index(idx: usize) -> Self;
+
+    /// Reads an element from `BITS / 8` bytes (little-endian)
+    fn read(bytes: &[u8]) -> Self;
+
+    /// Appends the `BITS / 8` bytes of the element (little-endian)
+    fn write(self, result: &mut Vec<u8>);
+}
+
+impl BinaryField for GF232 {
+    const BITS: usize = 32;
+
+    fn from_index(idx: usize) -> GF232 {
+        GF232(idx as u32)
+    }
+
+    fn read(bytes: &[u8]) -> GF232 {
+        GF232(
+            bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 |
+                (bytes[3] as u32) << 24,
+        )
+    }
+
+    fn write(self, result: &mut Vec<u8>) {
+        for i in 0..4 {
+            result.push((self.0 >> (8 * i)) as u8);
+        }
+    }
+}
+
+impl BinaryField for GF216 {
+    const BITS: usize = 16;
+
+    fn from_index(idx: usize) -> GF216 {
+        GF216(idx as u16)
+    }
+
+    fn read(bytes: &[u8]) -> GF216 {
+        GF216(bytes[0] as u16 | (bytes[1] as u16) << 8)
+    }
+
+    fn write(self, result: &mut Vec<u8>) {
+        result.push(self.0 as u8);
+        result.push((self.0 >> 8) as u8);
+    }
+}
+
+/// The additive FFT of Lin, Chung and Han over the subspaces `V_i = span(v_0, ...,
+/// v_(i-1))`. Polynomials are represented in their novel basis, `X_j = prod(W_i(x) /
+/// W_i(v_i))` over the bits `i` of `j`, where `W_i(x) = prod(x - a)` over `a` in `V_i`
+/// is the subspace vanishing polynomial. Since every `W_i` is linear, a polynomial with
+/// `2^t` coefficients can be evaluated at the `2^t` points `omega_(b + j)`, `j < 2^t`,
+/// in `O(2^t * t)` operations, for any shift `b` which is a multiple of `2^t`.
+#[derive(Clone, Debug)]
+pub struct AdditiveFft<F> {
+    /// `W_r(v_i) / W_r(v_r)` for all `r` and `i` less than the logarithm of the size
+    normalized: Vec<Vec<F>>,
+    /// The derivatives of the normalized `W_i`, which are constants
+    derivatives: Vec<F>,
+}
+
+impl<F: BinaryField> AdditiveFft<F> {
+    /// Prepares the transforms over the points `omega_j` for all `j < 2^log_size`
+    pub fn new(log_size: usize) -> AdditiveFft<F> {
+        assert!(log_size <= F::BITS);
+        // W_0(x) = x, W_(r+1)(x) = W_r(x) * (W_r(x) + W_r(v_r)) and W_(r+1)'(0) =
+        // W_r'(0) * W_r(v_r)
+        let mut values: Vec<F> = (0..log_size).map(|i| F::from_index(1 << i)).collect();
+        let mut derivative = F::from_index(1);
+        let mut normalized = vec![];
+        let mut derivatives = vec![];
+        for r in 0..log_size {
+            let at_vr = values[r];
+            normalized.push(values.iter().map(|w| *w / at_vr).collect());
+            derivatives.push(derivative / at_vr);
+            derivative = derivative * at_vr;
+            for w in &mut values {
+                *w = *w * (*w + at_vr);
+            }
+        }
+        AdditiveFft {
+            normalized,
+            derivatives,
+        }
+    }
+
+    /// The size of the largest transform
+    pub fn max_size(&self) -> usize {
+        1 << self.normalized.len()
+    }
+
+    /// Returns `W_r(omega_pos) / W_r(v_r)`
+    fn twiddle(&self, r: usize, pos: usize) -> F {
+        let mut result = F::from_index(0);
+        for (i, value) in self.normalized[r].iter().enumerate() {
+            if pos & (1 << i) != 0 {
+                result += *value;
+            }
+        }
+        result
+    }
+
+    /// Transforms the coefficients of a polynomial in the novel basis into its values at
+    /// the points `omega_(shift + j)`. The length of the data has to be a power of two
+    /// dividing the shift.
+    pub fn fft(&self, data: &mut [F], shift: usize) {
+        let len = data.len();
+        assert!(len.is_power_of_two() && shift.is_multiple_of(len));
+        assert!(shift + len <= self.max_size());
+        let mut half = len / 2;
+        while half > 0 {
+            let r = half.trailing_zeros() as usize;
+            for start in (0..len).step_by(2 * half) {
+                let twiddle = self.twiddle(r, shift + start);
+                for i in start..start + half {
+                    let high = data[i + half];
+                    data[i] += twiddle * high;
+                    data[i + half] = data[i] + high;
+                }
+            }
+            half /= 2;
+        }
+    }
+
+    /// The inverse of `fft`: transforms the values of a polynomial at the points
+    /// `omega_(shift + j)` into its coefficients in the novel basis
+    pub fn ifft(&self, data: &mut [F], shift: usize) {
+        let len = data.len();
+        assert!(len.is_power_of_two() && shift.is_multiple_of(len));
+        assert!(shift + len <= self.max_size());
+        let mut half = 1;
+        while half < len {
+            let r = half.trailing_zeros() as usize;
+            for start in (0..len).step_by(2 * half) {
+                let twiddle = self.twiddle(r, shift + start);
+                for i in start..start + half {
+                    let low = data[i];
+                    data[i + half] += low;
+                    data[i] = low + twiddle * data[i + half];
+                }
+            }
+            half *= 2;
+        }
+    }
+
+    /// Returns the formal derivative of a polynomial in the novel basis: the derivative
+    /// of `X_j` is the sum of `c_i * X_(j - 2^i)` over the bits `i` of `j`, where `c_i`
+    /// is the (constant) derivative of the `i`-th normalized `W_i`
+    pub fn derivative(&self, coefficients: &[F]) -> Vec<F> {
+        let mut result = vec![F::from_index(0); coefficients.len()];
+        for (i, c) in self.derivatives.iter().enumerate() {
+            let bit = 1 << i;
+            for j in (0..coefficients.len()).filter(|j| j & bit != 0) {
+                result[j ^ bit] += *c * coefficients[j];
+            }
+        }
+        result
+    }
+
+    /// Multiplies two polynomials in the novel basis by evaluating them at enough points
+    fn multiply(&self, a: &[F], b: &[F]) -> Vec<F> {
+        let len = (a.len() + b.len() - 1).next_power_of_two();
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        a.resize(len, F::from_index(0));
+        b.resize(len, F::from_index(0));
+        self.fft(&mut a, 0);
+        self.fft(&mut b, 0);
+        for (x, y) in a.iter_mut().zip(b) {
+            *x = *x * y;
+        }
+        self.ifft(&mut a, 0);
+        a
+    }
+
+    /// Returns the coefficients in the novel basis of the product of `x - omega_p` over
+    /// the given points, multiplying the factors pairwise in a tree
+    fn vanishing(&self, points: &[usize]) -> Vec<F> {
+        // x = X_1, since W_0(v_0) = 1
+        let mut factors: Vec<Vec<F>> = points
+            .iter()
+            .map(|&p| vec![F::from_index(p), F::from_index(1)])
+            .collect();
+        if factors.is_empty() {
+            return vec![F::from_index(1)];
+        }
+        while factors.len() > 1 {
+            factors = factors
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [ref a, ref b] => {
+                        let mut product = self.multiply(a, b);
+                        product.truncate(a.len() + b.len() - 1);
+                        product
+                    }
+                    _ => pair[0].clone(),
+                })
+                .collect();
+        }
+        factors.pop().unwrap()
+    }
+}
+
+/// Systematic Reed-Solomon code encoded and decoded with the additive FFT. The `k` data
+/// symbols of a stripe are the values of a polynomial of degree less than `K`, the
+/// power of two not less than `k`, at `omega_0, ..., omega_(k-1)` (with zeros at the
+/// rest of the first `K` points), and the `n - k` parities are its values at
+/// `omega_K, omega_(K+1), ...`. Encoding takes `O(n log K)` operations per stripe, and
+/// decoding `O(N log N)`, where `N` is the power of two not less than `K + n - k`, plus
+/// `O(N log^2 N)` once per erasure pattern to calculate the erasure locator.
+#[derive(Clone, Debug)]
+pub struct FftRs<F> {
+    n: usize,
+    k: usize,
+    fft: AdditiveFft<F>,
+}
+
+impl<F: BinaryField> FftRs<F> {
+    /// Creates a code with `n` shards, any `k` of which can recover the data. The
+    /// evaluation points of all the shards have to fit in the field.
+    pub fn new(n: usize, k: usize) -> Result<FftRs<F>, ConfigError> {
+        CodecConfig::new(n, k)?;
+        let size = (k.next_power_of_two() as u64 + (n - k) as u64).next_power_of_two();
+        if size > 1 << F::BITS {
+            return Err(ConfigError::Unsupported("too many shards for the field"));
+        }
+        Ok(FftRs {
+            n,
+            k,
+            fft: AdditiveFft::new(size.trailing_zeros() as usize),
+        })
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The index of the evaluation point of the given shard
+    fn point(&self, shard: usize) -> usize {
+        if shard < self.k {
+            shard
+        } else {
+            self.k.next_power_of_two() + shard - self.k
+        }
+    }
+
+    /// Encodes a single set of `k` symbols into `n`, the first `k` of which are the data
+    pub fn encode_stripe(&self, data: &[F]) -> Vec<F> {
+        assert_eq!(data.len(), self.k);
+        let size = self.k.next_power_of_two();
+        let mut coefficients = data.to_vec();
+        coefficients.resize(size, F::from_index(0));
+        self.fft.ifft(&mut coefficients, 0);
+        let mut result = data.to_vec();
+        let mut shift = size;
+        while result.len() < self.n {
+            let mut values = coefficients.clone();
+            self.fft.fft(&mut values, shift);
+            let needed = self.n - result.len();
+            result.extend(values.into_iter().take(needed));
+            shift += size;
+        }
+        result
+    }
+
+    /// Encodes the data into `n` shards. The length of the data is prepended to it, and
+    /// it is padded to a multiple of `k` symbols.
+    pub fn encode(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let bytes = F::BITS / 8;
+        let stripe_size = self.k * bytes;
+        let stream = frame(data, stripe_size);
+
+        let mut result = vec![vec![]; self.n];
+        for stripe in stream.chunks(stripe_size) {
+            let symbols: Vec<F> = stripe.chunks(bytes).map(F::read).collect();
+            for (shard, symbol) in result.iter_mut().zip(self.encode_stripe(&symbols)) {
+                symbol.write(shard);
+            }
+        }
+        result
+    }
+
+    /// Decodes the data from at least `k` shards
+    pub fn decode(&self, shards: &[(usize, &[u8])]) -> Result<Vec<u8>, DecodeError> {
+        let bytes = F::BITS / 8;
+        let length = validate_shards(shards, self.n, self.k, bytes)?;
+
+        let decoder = ErasureDecoder::new(self, shards.iter().map(|&(idx, _)| self.point(idx)));
+        let symbols: Vec<Vec<F>> = shards
+            .iter()
+            .map(|&(_, shard)| shard.chunks(bytes).map(F::read).collect())
+            .collect();
+        let mut stream = vec![];
+        for stripe in 0..length / bytes {
+            let mut received = vec![None; decoder.size];
+            for (&(idx, _), symbols) in shards.iter().zip(&symbols) {
+                received[self.point(idx)] = Some(symbols[stripe]);
+            }
+            for symbol in decoder.decode(&self.fft, &mut received) {
+                symbol.write(&mut stream);
+            }
+        }
+        unframe(&stream)
+    }
+}
+
+/// The state shared by all the stripes with the same erasures. If `g = f * P`, where `P`
+/// is the erasure locator, the product of `x - omega_e` over the missing points `e`, then
+/// `g` is known at all the points (being 0 at the missing ones), and `f(omega_e) =
+/// g'(omega_e) / P'(omega_e)`.
+struct ErasureDecoder<F> {
+    size: usize,
+    k: usize,
+    /// The values of `P` at all the points
+    locator: Vec<F>,
+    /// The missing data points along with the inverse of `P'` at them
+    missing: Vec<(usize, F)>,
+}
+
+impl<F: BinaryField> ErasureDecoder<F> {
+    fn new<I: Iterator<Item = usize>>(code: &FftRs<F>, received: I) -> ErasureDecoder<F> {
+        let size = code.fft.max_size();
+        let data_size = code.k.next_power_of_two();
+        let mut known = vec![false; size];
+        // the points between k and K are zeros which are never stored
+        for p in received.chain(code.k..data_size) {
+            known[p] = true;
+        }
+        let erased: Vec<usize> = (0..size).filter(|&p| !known[p]).collect();
+        let mut locator = code.fft.vanishing(&erased);
+        locator.resize(size, F::from_index(0));
+        let mut derivative = code.fft.derivative(&locator);
+        code.fft.fft(&mut locator, 0);
+        code.fft.fft(&mut derivative, 0);
+        let missing = (0..code.k)
+            .filter(|&p| !known[p])
+            .map(|p| (p, F::from_index(1) / derivative[p]))
+            .collect();
+        ErasureDecoder {
+            size,
+            k: code.k,
+            locator,
+            missing,
+        }
+    }
+
+    /// Recovers the `k` data symbols of a stripe from the received symbols at all the
+    /// points (the zeros between `k` and `K` are filled in here)
+    fn decode(&self, fft: &AdditiveFft<F>, received: &mut [Option<F>]) -> Vec<F> {
+        for symbol in &mut received[self.k..self.k.next_power_of_two()] {
+            *symbol = Some(F::from_index(0));
+        }
+        let mut result: Vec<F> = received[..self.k]
+            .iter()
+            .map(|s| s.unwrap_or_else(|| F::from_index(0)))
+            .collect();
+        if self.missing.is_empty() {
+            return result;
+        }
+        let mut values: Vec<F> = received
+            .iter()
+            .zip(&self.locator)
+            .map(|(s, p)| s.map_or(F::from_index(0), |s| s * *p))
+            .collect();
+        fft.ifft(&mut values, 0);
+        let mut derivative = fft.derivative(&values);
+        fft.fft(&mut derivative, 0);
+        for &(p, inverse) in &self.missing {
+            result[p] = derivative[p] * inverse;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use gf216::GF216;
+    use gf232::GF232;
+    use codec::DecodeError;
+    use config::ConfigError;
+    use super::{AdditiveFft, BinaryField, FftRs};
+
+    /// Evaluates a polynomial in the novel basis directly from the definition
+    fn evaluate_naive(coefficients: &[GF232], x: GF232) -> GF232 {
+        let w = |i: usize, x: GF232| {
+            let mut result = GF232(1);
+            for a in 0..1u32 << i {
+                result *= x + GF232(a);
+            }
+            result
+        };
+        let mut result = GF232(0);
+        for (j, c) in coefficients.iter().enumerate() {
+            let mut basis = GF232(1);
+            for i in (0..8).filter(|i| j & (1 << i) != 0) {
+                basis *= w(i, x) / w(i, GF232(1 << i));
+            }
+            result += *c * basis;
+        }
+        result
+    }
+
+    #[test]
+    fn test_fft() {
+        let mut rng = rand::thread_rng();
+        let fft = AdditiveFft::<GF232>::new(6);
+        let coefficients: Vec<GF232> = (0..16).map(|_| GF232(rng.gen())).collect();
+        for &shift in &[0, 16, 48] {
+            let mut values = coefficients.clone();
+            fft.fft(&mut values, shift);
+            for (j, value) in values.iter().enumerate() {
+                assert_eq!(*value, evaluate_naive(&coefficients, GF232((shift + j) as u32)));
+            }
+            fft.ifft(&mut values, shift);
+            assert_eq!(values, coefficients);
+        }
+
+        // the derivative of x^2 + x = W_1(x) is 1
+        let mut square: Vec<GF232> = (0..4).map(|j| GF232(j) * GF232(j) + GF232(j)).collect();
+        fft.ifft(&mut square, 0);
+        let mut derivative = fft.derivative(&square);
+        fft.fft(&mut derivative, 0);
+        assert_eq!(derivative, vec![GF232(1); 4]);
+    }
+
+    fn check_code<F: BinaryField>(n: usize, k: usize) {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let code = FftRs::<F>::new(n, k).unwrap();
+        let shards = code.encode(&data);
+        assert_eq!(shards.len(), n);
+        for _ in 0..5 {
+            let mut indices: Vec<usize> = (0..n).collect();
+            rng.shuffle(&mut indices);
+            let available: Vec<(usize, &[u8])> =
+                indices[..k].iter().map(|&idx| (idx, &shards[idx][..])).collect();
+            assert_eq!(code.decode(&available), Ok(data.clone()));
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        check_code::<GF232>(14, 10);
+        check_code::<GF232>(8, 8);
+        check_code::<GF216>(100, 37);
+        check_code::<GF216>(300, 256);
+    }
+
+    #[test]
+    fn test_short_stream() {
+        // two 2-byte shards can't even hold the length prefix
+        let code = FftRs::<GF216>::new(3, 2).unwrap();
+        let shards: Vec<(usize, &[u8])> = vec![(0, &[1, 2]), (2, &[3, 4])];
+        assert_eq!(code.decode(&shards), Err(DecodeError::InvalidShardLength));
+
+        // the length prefix claims more data than the shards hold
+        let shards = code.encode(&[5; 10]);
+        let mut tampered = shards.clone();
+        tampered[0][0] = 0xff;
+        let available: Vec<(usize, &[u8])> = vec![(0, &tampered[0]), (1, &tampered[1])];
+        assert_eq!(code.decode(&available), Err(DecodeError::InvalidShardLength));
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(FftRs::<GF216>::new(4, 0).err(), Some(ConfigError::NoDataShards));
+        assert_eq!(
+            FftRs::<GF216>::new(3, 4).err(),
+            Some(ConfigError::NotEnoughShards { n: 3, k: 4 })
+        );
+        // the data points take 32768 of the 65536 points, which leaves too few for the
+        // parity shards
+        assert!(FftRs::<GF216>::new(32769 + 32768, 32769).is_err());
+        assert!(FftRs::<GF216>::new(32768 + 32768, 32768).is_ok());
+    }
+}
@@ -0,0 +1,326 @@
+use codec::as_words;
+use gf232::GF232;
+use matrix::Matrix;
+use std::collections::HashMap;
+
+/// The number of packets every shard is split into within a block - one per bit of an
+/// element of the field. A packet is a single `u32`, so a block consists of 32 stripes.
+pub const PACKETS: usize = 32;
+
+/// Returns the 32x32 binary matrix of multiplication by `e`: bit `c` of the row `r` is
+/// set if bit `r` of `e * x^c` is set
+fn bitmatrix(e: GF232) -> [u32; PACKETS] {
+    let mut result = [0; PACKETS];
+    for c in 0..PACKETS {
+        let column = e * GF232(1 << c);
+        for (r, row) in result.iter_mut().enumerate() {
+            if column.0 & (1 << r) != 0 {
+                *row |= 1 << c;
+            }
+        }
+    }
+    result
+}
+
+/// The coefficients of the parity shards: `1 / (x_i + y_j)`, where `x_i = k + i` and
+/// `y_j = j` - every square submatrix of a Cauchy matrix is invertible, which makes the
+/// code MDS
+fn cauchy_matrix(n: usize, k: usize) -> Vec<Vec<GF232>> {
+    (k..n)
+        .map(|x| {
+            (0..k)
+                .map(|y| (GF232(x as u32) + GF232(y as u32)).inverse())
+                .collect()
+        })
+        .collect()
+}
+
+/// Expands a matrix over the field into the rows of a binary matrix, `PACKETS` rows per
+/// row of the original matrix, every row consisting of a mask per input shard
+fn expand(matrix: &[Vec<GF232>]) -> Vec<Vec<u32>> {
+    let mut result = vec![];
+    for row in matrix {
+        let bitmatrices: Vec<_> = row.iter().map(|e| bitmatrix(*e)).collect();
+        for r in 0..PACKETS {
+            result.push(bitmatrices.iter().map(|m| m[r]).collect());
+        }
+    }
+    result
+}
+
+/// The source of the packet XORed into an output packet
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Source {
+    /// The packet `c` of the input shard `j`
+    Input(usize, usize),
+    /// A previously calculated output packet
+    Output(usize),
+}
+
+/// A sequence of operations calculating the output packets of a binary matrix from the
+/// input packets. Every output packet is either calculated from scratch, or from an
+/// already calculated output packet differing from it in fewer input packets than it
+/// contains ("smart scheduling").
+#[derive(Clone, Debug)]
+struct XorSchedule {
+    /// For every output packet, the first source (copied) and the rest (XORed)
+    ops: Vec<(Source, Vec<Source>)>,
+}
+
+fn inputs(row: &[u32]) -> Vec<Source> {
+    let mut result = vec![];
+    for (j, mask) in row.iter().enumerate() {
+        for c in 0..PACKETS {
+            if mask & (1 << c) != 0 {
+                result.push(Source::Input(j, c));
+            }
+        }
+    }
+    result
+}
+
+fn weight(row: &[u32]) -> usize {
+    row.iter().map(|mask| mask.count_ones() as usize).sum()
+}
+
+impl XorSchedule {
+    /// Creates the schedule for the rows of a binary matrix. The rows can't be empty.
+    fn new(rows: &[Vec<u32>]) -> XorSchedule {
+        let mut ops = vec![];
+        for (o, row) in rows.iter().enumerate() {
+            let base = (0..o)
+                .map(|p| {
+                    let diff: Vec<u32> = row.iter().zip(&rows[p]).map(|(a, b)| a ^ b).collect();
+                    (p, diff)
+                })
+                .min_by_key(|(_, diff)| weight(diff));
+            let mut sources = inputs(row);
+            match base {
+                Some((p, diff)) if weight(&diff) + 1 < sources.len() => {
+                    ops.push((Source::Output(p), inputs(&diff)));
+                }
+                _ => {
+                    let first = sources.remove(0);
+                    ops.push((first, sources));
+                }
+            }
+        }
+        XorSchedule { ops }
+    }
+
+    /// The number of XORs of packets performed by the schedule
+    #[cfg(test)]
+    fn xors(&self) -> usize {
+        self.ops.iter().map(|(_, rest)| rest.len()).sum()
+    }
+
+    /// Calculates the output packets of all the blocks. `inputs` are the shards
+    /// converted to `u32`s; the result contains the output shards.
+    fn apply(&self, inputs: &[Vec<u32>]) -> Vec<Vec<u32>> {
+        let blocks = inputs[0].len() / PACKETS;
+        let mut outputs: Vec<Vec<u32>> = vec![];
+        for &(first, ref rest) in &self.ops {
+            let mut packet = vec![0; blocks];
+            for &source in Some(&first).into_iter().chain(rest) {
+                for (b, x) in packet.iter_mut().enumerate() {
+                    *x ^= match source {
+                        Source::Input(j, c) => inputs[j][b * PACKETS + c],
+                        Source::Output(p) => outputs[p][b],
+                    };
+                }
+            }
+            outputs.push(packet);
+        }
+        outputs
+            .chunks(PACKETS)
+            .map(|packets| {
+                (0..blocks * PACKETS)
+                    .map(|w| packets[w % PACKETS][w / PACKETS])
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn as_u32s(shard: &[u8]) -> Vec<u32> {
+    as_words(shard).into_iter().map(|x| x.0).collect()
+}
+
+fn push_u32s(shard: &mut Vec<u8>, words: &[u32]) {
+    for x in words {
+        shard.push((x & 0xFF) as u8);
+        shard.push(((x >> 8) & 0xFF) as u8);
+        shard.push(((x >> 16) & 0xFF) as u8);
+        shard.push(((x >> 24) & 0xFF) as u8);
+    }
+}
+
+/// Encodes a stream of bytes, the length of which has to be a multiple of `PACKETS`
+/// stripes of `k` `u32`s, appending the results to the given shards. The data shards
+/// get the same contents as with the interpolation encoder, and the parity shards are
+/// calculated using only XORs.
+pub fn encode_into(data: &[u8], shards: &mut [Vec<u8>], k: usize) {
+    let n = shards.len();
+    let words = as_u32s(data);
+    let data_shards: Vec<Vec<u32>> = (0..k)
+        .map(|j| words.iter().skip(j).step_by(k).cloned().collect())
+        .collect();
+    let parity = if n > k {
+        XorSchedule::new(&expand(&cauchy_matrix(n, k))).apply(&data_shards)
+    } else {
+        vec![]
+    };
+    for (shard, words) in shards.iter_mut().zip(data_shards.iter().chain(&parity)) {
+        push_u32s(shard, words);
+    }
+}
+
+/// Decoder of the blocks of shards created by `encode_into`. The inverse of the
+/// matrix of the shards used and the schedule recovering the missing data shards
+/// from them are calculated only once for every set of shards.
+pub struct StreamDecoder {
+    n: usize,
+    k: usize,
+    /// The schedules for the lists of indices of the shards used for decoding
+    schedules: HashMap<Vec<usize>, XorSchedule>,
+}
+
+impl StreamDecoder {
+    pub fn new(n: usize, k: usize) -> StreamDecoder {
+        StreamDecoder {
+            n,
+            k,
+            schedules: HashMap::new(),
+        }
+    }
+
+    /// Returns the schedule recovering the data shards missing from the given ones
+    fn schedule(&mut self, indices: Vec<usize>) -> &XorSchedule {
+        let (n, k) = (self.n, self.k);
+        self.schedules.entry(indices).or_insert_with_key(|indices| {
+            let cauchy = cauchy_matrix(n, k);
+            let rows = indices
+                .iter()
+                .map(|&idx| if idx < k {
+                    (0..k).map(|j| GF232((j == idx) as u32)).collect()
+                } else {
+                    cauchy[idx - k].clone()
+                })
+                .collect();
+            let inverse = Matrix::new(rows)
+                .inverse()
+                .expect("Any k shards should be able to recover the data");
+            let matrix: Vec<Vec<GF232>> = (0..k)
+                .filter(|j| !indices.contains(j))
+                .map(|j| inverse[j].to_vec())
+                .collect();
+            XorSchedule::new(&expand(&matrix))
+        })
+    }
+
+    /// Decodes `k` shards into the stream of bytes they encode
+    pub fn decode(&mut self, data: &[(usize, &[u8])]) -> Vec<u8> {
+        let k = self.k;
+        assert!(data.len() >= k);
+        let data = &data[..k];
+        let indices: Vec<usize> = data.iter().map(|&(idx, _)| idx).collect();
+        let inputs: Vec<Vec<u32>> = data.iter().map(|&(_, shard)| as_u32s(shard)).collect();
+        let mut recovered = if indices.iter().all(|&idx| idx < k) {
+            vec![]
+        } else {
+            self.schedule(indices.clone()).apply(&inputs)
+        };
+        recovered.reverse();
+        let mut data_shards = vec![];
+        for j in 0..k {
+            match indices.iter().position(|&idx| idx == j) {
+                Some(pos) => data_shards.push(inputs[pos].clone()),
+                None => data_shards.push(recovered.pop().unwrap()),
+            }
+        }
+
+        let mut result = vec![];
+        for w in 0..inputs[0].len() {
+            for shard in &data_shards {
+                push_u32s(&mut result, &[shard[w]]);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use gf232::GF232;
+    use super::{cauchy_matrix, encode_into, expand, StreamDecoder, XorSchedule, PACKETS};
+
+    #[test]
+    fn test_bit_slicing() {
+        let mut rng = rand::thread_rng();
+        let (n, k) = (7, 4);
+        let data: Vec<u8> = rng.gen_iter().take(k * PACKETS * 4 * 3).collect();
+        let mut shards = vec![vec![]; n];
+        encode_into(&data, &mut shards, k);
+        let word = |shard: &[u8], w: usize| {
+            shard[4 * w] as u32 | (shard[4 * w + 1] as u32) << 8 |
+                (shard[4 * w + 2] as u32) << 16 | (shard[4 * w + 3] as u32) << 24
+        };
+        // bit `t` of the packets `c` of a block form the coefficients of an element of
+        // the field, and the parity elements are the Cauchy combinations of the data ones
+        let cauchy = cauchy_matrix(n, k);
+        for block in 0..3 {
+            for t in 0..32 {
+                let element = |shard: &[u8]| {
+                    let mut result = 0;
+                    for c in 0..PACKETS {
+                        result |= ((word(shard, block * PACKETS + c) >> t) & 1) << c;
+                    }
+                    GF232(result)
+                };
+                for i in 0..n - k {
+                    let mut expected = GF232(0);
+                    for j in 0..k {
+                        expected += cauchy[i][j] * element(&shards[j]);
+                    }
+                    assert_eq!(element(&shards[k + i]), expected);
+                }
+            }
+        }
+
+        let mut decoder = StreamDecoder::new(n, k);
+        for subset in 0..128u32 {
+            if subset.count_ones() != 4 {
+                continue;
+            }
+            let selected: Vec<(usize, &[u8])> = (0..n)
+                .filter(|&idx| subset & (1 << idx) != 0)
+                .map(|idx| (idx, &shards[idx][..]))
+                .collect();
+            assert_eq!(decoder.decode(&selected), data);
+            // every block decoded separately, with the same schedule
+            let mut blocks = vec![];
+            for block in 0..3 {
+                let range = block * PACKETS * 4..(block + 1) * PACKETS * 4;
+                let selected: Vec<(usize, &[u8])> = selected
+                    .iter()
+                    .map(|&(idx, shard)| (idx, &shard[range.clone()]))
+                    .collect();
+                blocks.extend(decoder.decode(&selected));
+            }
+            assert_eq!(blocks, data);
+        }
+        // one schedule for every set of shards containing a parity shard
+        assert_eq!(decoder.schedules.len(), 34);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let rows = expand(&cauchy_matrix(14, 10));
+        let schedule = XorSchedule::new(&rows);
+        let naive: usize = rows.iter()
+            .map(|row| row.iter().map(|m| m.count_ones() as usize).sum::<usize>() - 1)
+            .sum();
+        assert!(schedule.xors() < naive);
+    }
+}
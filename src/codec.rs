@@ -1,9 +1,18 @@
+use cauchy;
+use config::{Checksum, Coding, CodecConfig, ConfigError, Framing, Layout};
+use crc::Crc;
 use gf232::GF232;
-use polygf232::PolyGF232;
+use merkle::{self, MerkleTree};
+use polygf232::{Evaluation, Interpolation, PolyGF232};
+use sha256::Hash;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
 use std::iter;
 
 /// Converts a `u64` to a byte array (little-endian)
-fn as_bytes(mut x: u64) -> Vec<u8> {
+pub fn as_bytes(mut x: u64) -> Vec<u8> {
     let mut result = vec![];
     for _ in 0..8 {
         result.push((x & 0xFF) as u8);
@@ -13,14 +22,100 @@ fn as_bytes(mut x: u64) -> Vec<u8> {
 }
 
 /// Converts an 8-byte array to a `u64` (little-endian)
-fn as_u64(bytes: &[u8]) -> u64 {
+pub fn as_u64(bytes: &[u8]) -> u64 {
     let mut result = 0;
-    for i in 0..8 {
-        result |= (bytes[i] as u64) << i * 8;
+    for (i, byte) in bytes[..8].iter().enumerate() {
+        result |= (*byte as u64) << (i * 8);
     }
     result
 }
 
+/// Converts a shard into field elements (little-endian)
+pub fn as_words(shard: &[u8]) -> Vec<GF232> {
+    shard
+        .chunks(4)
+        .map(|bytes| {
+            GF232(
+                bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 |
+                    (bytes[3] as u32) << 24,
+            )
+        })
+        .collect()
+}
+
+/// Converts field elements back into a shard
+pub fn as_shard(words: &[GF232]) -> Vec<u8> {
+    let mut result = vec![];
+    for word in words {
+        result.push((word.0 & 0xFF) as u8);
+        result.push(((word.0 >> 8) & 0xFF) as u8);
+        result.push(((word.0 >> 16) & 0xFF) as u8);
+        result.push(((word.0 >> 24) & 0xFF) as u8);
+    }
+    result
+}
+
+/// Prepends the length of the data to it as 8 little-endian bytes, and pads it with 0's
+/// to a multiple of `stripe_size` bytes
+pub fn frame(data: &[u8], stripe_size: usize) -> Vec<u8> {
+    let mut stream = as_bytes(data.len() as u64);
+    stream.extend(data);
+    let padding = (stripe_size - stream.len() % stripe_size) % stripe_size;
+    let length = stream.len() + padding;
+    stream.resize(length, 0);
+    stream
+}
+
+/// Extracts the data from a decoded stream starting with its length, like the ones
+/// created by `frame`
+pub fn unframe(stream: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if stream.len() < 8 || as_u64(&stream[0..8]) > (stream.len() - 8) as u64 {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    let size = as_u64(&stream[0..8]) as usize;
+    Ok(stream[8..8 + size].to_vec())
+}
+
+/// Checks that the indices of the shards are less than `n` and distinct
+fn check_indices(shards: &[(usize, &[u8])], n: usize) -> Result<(), DecodeError> {
+    let mut indices = HashSet::new();
+    for &(idx, _) in shards {
+        if idx >= n {
+            return Err(DecodeError::InvalidShardIndex(idx));
+        }
+        if !indices.insert(idx) {
+            return Err(DecodeError::DuplicateShardIndex(idx));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the shards have valid and distinct indices, that there are at least
+/// `required` of them, and at least one, and that they are all of the same nonzero
+/// length, which is a multiple of `unit` bytes. Returns the length.
+pub fn validate_shards(
+    shards: &[(usize, &[u8])],
+    n: usize,
+    required: usize,
+    unit: usize,
+) -> Result<usize, DecodeError> {
+    check_indices(shards, n)?;
+    let required = cmp::max(required, 1);
+    if shards.len() < required {
+        return Err(DecodeError::NotEnoughShards {
+            found: shards.len(),
+            required,
+        });
+    }
+    let length = shards[0].1.len();
+    if length == 0 || !length.is_multiple_of(unit) ||
+        shards.iter().any(|&(_, shard)| shard.len() != length)
+    {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    Ok(length)
+}
+
 /// Struct iterating through an iterator returning bytes and converting them
 /// into `u32`s on the fly (little-endian)
 struct IterU32<I: Iterator<Item = u8>> {
@@ -83,84 +178,525 @@ impl<I: Iterator> Iterator for TakeN<I> {
 }
 
 /// Returns a polynomial interpolating the given points
-fn interpolate(points: Vec<(usize, GF232)>) -> PolyGF232 {
-    let mut result = PolyGF232::new(vec![]);
-    let _x = PolyGF232::new(vec![GF232(0), GF232(1)]);
-    for p in &points {
-        let mut coefficient = PolyGF232::new(vec![GF232(1)]);
-        for p2 in &points {
-            if *p2 == *p {
-                continue;
+pub fn interpolate(points: Vec<(usize, GF232)>) -> PolyGF232 {
+    let points: Vec<(GF232, GF232)> = points.into_iter().map(|(x, y)| (GF232(x as u32), y)).collect();
+    PolyGF232::interpolate(&points)
+}
+
+/// Returns the coefficients with which the data word at index `j` of a stripe
+/// contributes to the encoded words at the indices `xs`, none of which is less than
+/// `k` - that is, the values of the `j`-th Lagrange basis polynomial over the points
+/// `0..k` at the `xs`. In the barycentric form the value at `x` is
+/// `l(x) / ((x - j) * prod(j - m))`, where `l(x)` is the product of all the `x - m`,
+/// so all the denominators are inverted together.
+fn lagrange_coefficients(j: usize, xs: &[usize], k: usize) -> Vec<GF232> {
+    let j_point = GF232(j as u32);
+    let mut denominator = GF232(1);
+    for m in (0..k).filter(|&m| m != j) {
+        denominator *= j_point + GF232(m as u32);
+    }
+    let mut inverses: Vec<GF232> = xs
+        .iter()
+        .map(|&x| (GF232(x as u32) + j_point) * denominator)
+        .collect();
+    GF232::batch_inverse(&mut inverses);
+    xs.iter()
+        .zip(inverses)
+        .map(|(&x, inverse)| {
+            let mut numerator = GF232(1);
+            for m in 0..k {
+                numerator *= GF232(x as u32) + GF232(m as u32);
             }
-            let l = GF232(p2.0 as u32);
-            let j = GF232(p.0 as u32);
-            coefficient = coefficient * (&_x + l) / (j + l);
+            numerator * inverse
+        })
+        .collect()
+}
+
+/// Encodes stripes of `k` `u32`s into `n` `u32`s each. The interpolation through the
+/// points `0..k` and the evaluation at the points `0..n` are prepared once for all the
+/// stripes.
+pub struct StripeEncoder {
+    k: usize,
+    interpolation: Interpolation,
+    evaluation: Evaluation,
+}
+
+impl StripeEncoder {
+    pub fn new(n: usize, k: usize) -> StripeEncoder {
+        let points: Vec<GF232> = (0..n).map(|i| GF232(i as u32)).collect();
+        StripeEncoder {
+            k,
+            interpolation: Interpolation::new(&points[..k]),
+            evaluation: Evaluation::new(&points, k),
         }
-        result = result + coefficient * p.1;
     }
-    result
+
+    /// Encodes a single set of `k` `u32`s
+    pub fn encode(&self, data: &[u32]) -> Vec<u32> {
+        assert_eq!(data.len(), self.k);
+        let values: Vec<GF232> = data.iter().cloned().map(GF232).collect();
+        let interpolated = self.interpolation.interpolate(&values);
+        self.evaluation.evaluate(&interpolated).into_iter().map(|x| x.0).collect()
+    }
 }
 
-/// Encodes a single set of `k` `u32`s
-fn encode_stripe(data: &[u32], n: usize, k: usize) -> Vec<u32> {
-    assert_eq!(data.len(), k);
-    let interpolated = interpolate(data.into_iter().cloned().map(GF232).enumerate().collect());
-    let mut result = vec![];
-    for i in 0..n {
-        let point = interpolated.apply(GF232(i as u32));
-        result.push(point.0);
+/// Decodes stripes of `k` `u32`s from the words of the same `k` shards. The
+/// interpolation through the indices of the shards and the evaluation at the points
+/// `0..k` are prepared once for all the stripes.
+pub struct StripeDecoder {
+    indices: Vec<usize>,
+    interpolation: Interpolation,
+    evaluation: Evaluation,
+}
+
+impl StripeDecoder {
+    /// Prepares decoding from the shards with the first `k` of the given indices
+    pub fn new(indices: &[usize], k: usize) -> StripeDecoder {
+        assert!(indices.len() >= k);
+        let points: Vec<GF232> = indices[..k].iter().map(|&x| GF232(x as u32)).collect();
+        let targets: Vec<GF232> = (0..k).map(|i| GF232(i as u32)).collect();
+        StripeDecoder {
+            indices: indices[..k].to_vec(),
+            interpolation: Interpolation::new(&points),
+            evaluation: Evaluation::new(&targets, k),
+        }
+    }
+
+    /// Decodes a single set of `k` `u32`s from the words of the shards, the first `k`
+    /// of which have to come from the shards the decoder was prepared for
+    pub fn decode(&self, data: &[(usize, u32)]) -> Vec<u32> {
+        assert!(data.len() >= self.indices.len());
+        let values: Vec<GF232> = data.iter()
+            .zip(&self.indices)
+            .map(|(&(idx, y), &expected)| {
+                assert_eq!(idx, expected);
+                GF232(y)
+            })
+            .collect();
+        let interpolated = self.interpolation.interpolate(&values);
+        self.evaluation.evaluate(&interpolated).into_iter().map(|x| x.0).collect()
     }
-    result
 }
 
-/// Decodes a single set of `k` `u32`s
-fn decode_stripe(data: &[(usize, u32)], k: usize) -> Vec<u32> {
-    assert!(data.len() >= k);
-    let interpolated = interpolate(
-        data.into_iter()
-            .take(k)
-            .cloned()
-            .map(|(x, y)| (x, GF232(y)))
-            .collect(),
-    );
-    let mut result = vec![];
-    for i in 0..k {
-        result.push(interpolated.apply(GF232(i as u32)).0);
+/// Encodes a single set of `k` `u32`s. Use a `StripeEncoder` to encode many stripes.
+pub fn encode_stripe(data: &[u32], n: usize, k: usize) -> Vec<u32> {
+    StripeEncoder::new(n, k).encode(data)
+}
+
+/// Encodes a stream of bytes, the length of which has to be a multiple of `k`
+/// `u32`s, appending the results to the given shards (one per each of the `n`
+/// encoded words)
+fn encode_into<I: Iterator<Item = u8>>(data: I, shards: &mut [Vec<u8>], k: usize) {
+    let encoder = StripeEncoder::new(shards.len(), k);
+    let stripes = TakeN::new(IterU32 { inner: data }, k);
+    for stripe in stripes {
+        let encoded = encoder.encode(&stripe);
+        for (v, val) in shards.iter_mut().zip(encoded) {
+            v.push((val & 0xFF) as u8);
+            v.push(((val >> 8) & 0xFF) as u8);
+            v.push(((val >> 16) & 0xFF) as u8);
+            v.push(((val >> 24) & 0xFF) as u8);
+        }
+    }
+}
+
+/// Reorders the `u32`s of a stream, the length of which is a multiple of `k` `u32`s,
+/// from the given layout into the order of the stripes - or, if `inverse` is true,
+/// from the order of the stripes into the layout
+fn reorder(data: Vec<u8>, k: usize, layout: Layout, inverse: bool) -> Vec<u8> {
+    match layout {
+        Layout::Interleaved => data,
+        Layout::Blocks => {
+            let m = data.len() / (4 * k);
+            let mut result = vec![0; data.len()];
+            for j in 0..k {
+                for i in 0..m {
+                    let (block_pos, stripe_pos) = ((j * m + i) * 4, (i * k + j) * 4);
+                    let (from, to) = if inverse {
+                        (stripe_pos, block_pos)
+                    } else {
+                        (block_pos, stripe_pos)
+                    };
+                    result[to..to + 4].copy_from_slice(&data[from..from + 4]);
+                }
+            }
+            result
+        }
     }
-    result
 }
 
 /// Encodes a given array of bytes using striping
 /// The length of the data is prepended to the array, and a padding of 0's is appended
 /// in order to make sure that the data length is a multiple of `k` `u32`s.
 pub fn encode(data: &[u8], n: usize, k: usize) -> Vec<Vec<u8>> {
-    let stripe_size = k * 4;
-    let padding = stripe_size - (8 + data.len()) % stripe_size;
-    let length = 8 + data.len() + padding;
-    let data_stream = IterU32 {
-        inner: as_bytes(data.len() as u64)
-            .into_iter()
-            .chain(data.into_iter().cloned())
-            .chain(iter::repeat(0))
-            .take(length),
+    let config = CodecConfig::new(n, k).expect("Invalid coding parameters!");
+    encode_with_config(data, &config)
+}
+
+/// Encodes a given array of bytes into `config.n()` shards, using the layout and the
+/// framing specified by the configuration
+pub fn encode_with_config(data: &[u8], config: &CodecConfig) -> Vec<Vec<u8>> {
+    encode_shards(data, config).0
+}
+
+/// Encodes a given array of bytes like `encode_with_config`, additionally returning the
+/// root of the Merkle tree over the shards, which can be used to verify the proofs in
+/// their headers. Returns an error if the configuration doesn't have the Merkle proofs
+/// enabled.
+pub fn encode_with_commitment(
+    data: &[u8],
+    config: &CodecConfig,
+) -> Result<(Vec<Vec<u8>>, Hash), ConfigError> {
+    match encode_shards(data, config) {
+        (shards, Some(root)) => Ok((shards, root)),
+        (_, None) => Err(ConfigError::NoMerkleProofs),
+    }
+}
+
+/// Encodes the data into the shards, returning the root of the Merkle tree over them
+/// if the configuration requires the shards to contain Merkle proofs
+fn encode_shards(data: &[u8], config: &CodecConfig) -> (Vec<Vec<u8>>, Option<Hash>) {
+    let k = config.k();
+    let stripe_size = k * 4 * config.coding().stripes_per_block();
+    let mut data_stream = vec![];
+    let padding = match config.framing() {
+        Framing::Header => {
+            data_stream.extend(as_bytes(data.len() as u64));
+            stripe_size - (8 + data.len()) % stripe_size
+        }
+        Framing::Trailer => (stripe_size - data.len() % stripe_size) % stripe_size,
     };
-    let stripes = TakeN::new(data_stream, k);
+    data_stream.extend(data);
+    let length = data_stream.len() + padding;
+    data_stream.resize(length, 0);
     let mut result = vec![];
-    for _ in 0..n {
+    for _ in 0..config.n() {
         result.push(vec![]);
     }
-    for stripe in stripes {
-        let encoded = encode_stripe(&stripe, n, k);
-        for (v, val) in result.iter_mut().zip(encoded) {
-            v.push((val & 0xFF) as u8);
-            v.push(((val >> 8) & 0xFF) as u8);
-            v.push(((val >> 16) & 0xFF) as u8);
-            v.push(((val >> 24) & 0xFF) as u8);
+    let data_stream = reorder(data_stream, k, config.layout(), false);
+    match config.coding() {
+        Coding::Interpolation => encode_into(data_stream.into_iter(), &mut result, k),
+        Coding::CauchyXor => cauchy::encode_into(&data_stream, &mut result, k),
+    }
+    if config.framing() == Framing::Trailer {
+        for shard in &mut result {
+            shard.extend(as_bytes(data.len() as u64));
+        }
+    }
+    if let Some(crc) = checksum_crc(config.checksum()) {
+        for shard in &mut result {
+            *shard = add_checksums(shard, &crc, config.checksum_block_size());
         }
     }
+    if !config.merkle_proofs() {
+        return (result, None);
+    }
+    let leaves = result
+        .iter()
+        .enumerate()
+        .map(|(idx, shard)| shard_leaf_hash(idx, shard))
+        .collect();
+    let tree = MerkleTree::new(leaves);
+    for (idx, shard) in result.iter_mut().enumerate() {
+        let proof = tree.proof(idx);
+        let mut header = vec![proof.len() as u8];
+        for hash in proof {
+            header.extend(&hash);
+        }
+        header.extend(&shard[..]);
+        *shard = header;
+    }
+    (result, Some(tree.root()))
+}
+
+/// Returns the hash of a shard (without the header) as a leaf of the Merkle tree. The
+/// index is hashed as well, so that a shard can't be used in place of another one.
+fn shard_leaf_hash(idx: usize, shard: &[u8]) -> Hash {
+    let mut leaf = as_bytes(idx as u64);
+    leaf.extend(shard);
+    merkle::leaf_hash(&leaf)
+}
+
+/// Splits a shard into the Merkle proof contained in its header and the rest of the
+/// shard. Returns `None` if the header is truncated.
+fn split_merkle_header(shard: &[u8]) -> Option<(Vec<Hash>, &[u8])> {
+    let count = *shard.first()? as usize;
+    if shard.len() < 1 + count * 32 {
+        return None;
+    }
+    let mut proof = vec![];
+    for i in 0..count {
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&shard[1 + i * 32..1 + (i + 1) * 32]);
+        proof.push(hash);
+    }
+    Some((proof, &shard[1 + count * 32..]))
+}
+
+/// Returns the CRC implementing the given checksum algorithm
+fn checksum_crc(checksum: Checksum) -> Option<Crc> {
+    match checksum {
+        Checksum::None => None,
+        Checksum::Crc32c => Some(Crc::crc32c()),
+        Checksum::Crc64 => Some(Crc::crc64()),
+    }
+}
+
+/// Splits the contents of a shard into blocks, following every block with its
+/// checksum
+fn add_checksums(shard: &[u8], crc: &Crc, block_size: usize) -> Vec<u8> {
+    let checksum_size = crc.width() as usize / 8;
+    let mut result = vec![];
+    for block in shard.chunks(block_size) {
+        result.extend(block);
+        result.extend(&as_bytes(crc.checksum(block))[..checksum_size]);
+    }
     result
 }
 
+/// Splits a shard created with checksums into blocks and verifies them, returning
+/// the contents of the shard without the checksums, along with flags indicating
+/// which blocks are intact. Returns `None` if the shard is of an invalid length.
+fn verify_checksums(shard: &[u8], crc: &Crc, block_size: usize) -> Option<(Vec<u8>, Vec<bool>)> {
+    let checksum_size = crc.width() as usize / 8;
+    let mut contents = vec![];
+    let mut intact = vec![];
+    for block in shard.chunks(block_size + checksum_size) {
+        if block.len() <= checksum_size {
+            return None;
+        }
+        let (block, checksum) = block.split_at(block.len() - checksum_size);
+        let mut expected = checksum.to_vec();
+        expected.resize(8, 0);
+        intact.push(crc.checksum(block) == as_u64(&expected));
+        contents.extend(block);
+    }
+    Some((contents, intact))
+}
+
+/// Checks whether all the checksums in a shard created by `encode_with_config` are
+/// correct. Shards without checksums are always considered correct.
+pub fn verify_shard(shard: &[u8], config: &CodecConfig) -> bool {
+    match checksum_crc(config.checksum()) {
+        None => true,
+        Some(crc) => {
+            match verify_checksums(shard, &crc, config.checksum_block_size()) {
+                Some((_, intact)) => intact.into_iter().all(|x| x),
+                None => false,
+            }
+        }
+    }
+}
+
+/// Encodes a given array of bytes using striping, in a layout allowing for
+/// more data to be appended later with `append`.
+/// Instead of being prepended to the data, the length is stored in an 8-byte
+/// trailer at the end of every shard. The data is only padded with 0's up to
+/// the end of the last stripe, so that it starts at the beginning of the
+/// shards and stays aligned to the stripes.
+pub fn encode_appendable(data: &[u8], n: usize, k: usize) -> Vec<Vec<u8>> {
+    let config = CodecConfig::builder(n, k)
+        .framing(Framing::Trailer)
+        .build()
+        .expect("Invalid coding parameters!");
+    encode_with_config(data, &config)
+}
+
+/// Encodes the given data padded with 0's to a multiple of `k` `u32`s,
+/// appending the results to the shards
+fn append_stripes(data: &[u8], shards: &mut [Vec<u8>], k: usize) {
+    let stripe_size = k * 4;
+    let padding = (stripe_size - data.len() % stripe_size) % stripe_size;
+    let data_stream = data.iter().cloned().chain(iter::repeat(0)).take(
+        data.len() + padding,
+    );
+    encode_into(data_stream, shards, k);
+}
+
+/// Appends more data to all `n` shards created by `encode_appendable`.
+/// Only the last, partially filled stripe is re-encoded (its contents are read
+/// back from the first `k` shards, which contain the data verbatim), all the
+/// earlier stripes stay untouched.
+/// Returns an error, leaving the shards unchanged, if `k` is invalid, or if the shards
+/// are not all of the same length or their length doesn't agree with the length in
+/// their trailers.
+pub fn append(shards: &mut [Vec<u8>], more_data: &[u8], k: usize) -> Result<(), DecodeError> {
+    if shards.len() < k {
+        return Err(DecodeError::NotEnoughShards {
+            found: shards.len(),
+            required: k,
+        });
+    }
+    CodecConfig::new(shards.len(), k).map_err(DecodeError::InvalidConfig)?;
+    let shard_len = shards[0].len();
+    if shard_len < 8 || !(shard_len - 8).is_multiple_of(4) ||
+        shards.iter().any(|shard| shard.len() != shard_len)
+    {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    let trailer_start = shard_len - 8;
+    let trailer = &shards[0][trailer_start..];
+    if shards.iter().any(|shard| &shard[trailer_start..] != trailer) {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    let stripe_size = k * 4;
+    let length = as_u64(trailer);
+    if length.div_ceil(stripe_size as u64) != (trailer_start / 4) as u64 {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    let length = length as usize;
+    let full_stripes = length / stripe_size;
+    let partial = length % stripe_size;
+    let mut tail = vec![];
+    if partial > 0 {
+        for shard in shards.iter().take(k) {
+            tail.extend(&shard[full_stripes * 4..full_stripes * 4 + 4]);
+        }
+        tail.truncate(partial);
+    }
+    tail.extend(more_data);
+    for shard in shards.iter_mut() {
+        shard.truncate(full_stripes * 4);
+    }
+    append_stripes(&tail, shards, k);
+    for shard in shards.iter_mut() {
+        shard.extend(as_bytes((length + more_data.len()) as u64));
+    }
+    Ok(())
+}
+
+/// Updates the parity shards in place after some words of a single data shard
+/// have changed, without re-encoding the whole object.
+/// `old_data_words` and `new_data_words` are the previous and the new contents
+/// of consecutive words of the data shard `data_index` (which has to be less
+/// than `k`), starting with the word belonging to the stripe `stripe`.
+/// Every entry in `parity_shards` is a pair of a shard index (in `k..n`) and the
+/// shard's contents, which get the difference between the old and the new data,
+/// multiplied by the appropriate coefficient, added to them. The checksums of the
+/// changed blocks are recalculated, after verifying that the blocks were intact.
+/// Shards with Merkle proofs or encoded with `Coding::CauchyXor` can't be updated in
+/// place and have to be re-encoded.
+/// If an error is returned, no shard is modified.
+/// Note that with `Framing::Header` the first stripe starts with the 8-byte length of
+/// the data, so that with `Layout::Interleaved` the word at byte offset `4 * w` of the
+/// data stream is located at stripe `w / k` in the data shard `w % k`, where
+/// `w = (offset + 8) / 4`.
+pub fn update_parity(
+    old_data_words: &[u32],
+    new_data_words: &[u32],
+    stripe: usize,
+    data_index: usize,
+    parity_shards: &mut [(usize, &mut [u8])],
+    config: &CodecConfig,
+) -> Result<(), UpdateError> {
+    assert_eq!(old_data_words.len(), new_data_words.len());
+    if config.merkle_proofs() || config.coding() != Coding::Interpolation {
+        return Err(UpdateError::UnsupportedConfig);
+    }
+    let (n, k) = (config.n(), config.k());
+    if data_index >= k {
+        return Err(UpdateError::InvalidShardIndex(data_index));
+    }
+    let crc = checksum_crc(config.checksum());
+    let checksum_size = config.checksum().size();
+    let block_size = match crc {
+        Some(_) => config.checksum_block_size(),
+        None => usize::MAX - checksum_size,
+    };
+    // the range of a block of the shard without its checksum
+    let block_range = |block: usize, shard_len: usize| {
+        let start = block * (block_size + checksum_size);
+        (start, cmp::min(start + block_size, shard_len - checksum_size))
+    };
+    let position = |offset: usize| {
+        offset / block_size * (block_size + checksum_size) + offset % block_size
+    };
+    let start = stripe * 4;
+    let end = (stripe + new_data_words.len()) * 4;
+    let trailer = match config.framing() {
+        Framing::Header => 0,
+        Framing::Trailer => 8,
+    };
+    let blocks = start / block_size..end.div_ceil(block_size);
+    for &(idx, ref shard) in parity_shards.iter() {
+        if idx < k || idx >= n {
+            return Err(UpdateError::InvalidShardIndex(idx));
+        }
+        let full_blocks = shard.len() / (block_size + checksum_size);
+        let rest = shard.len() % (block_size + checksum_size);
+        if rest > 0 && rest <= checksum_size {
+            return Err(UpdateError::InvalidShardLength);
+        }
+        let contents_len = full_blocks * block_size + rest.saturating_sub(checksum_size);
+        if end + trailer > contents_len {
+            return Err(UpdateError::InvalidShardLength);
+        }
+        if let Some(ref crc) = crc {
+            for block in blocks.clone() {
+                let (block_start, block_end) = block_range(block, shard.len());
+                let mut expected = shard[block_end..block_end + checksum_size].to_vec();
+                expected.resize(8, 0);
+                if crc.checksum(&shard[block_start..block_end]) != as_u64(&expected) {
+                    return Err(UpdateError::CorruptedBlock { shard: idx, block });
+                }
+            }
+        }
+    }
+    let indices: Vec<usize> = parity_shards.iter().map(|&(idx, _)| idx).collect();
+    let coefficients = lagrange_coefficients(data_index, &indices, k);
+    for (&mut (_, ref mut shard), coefficient) in parity_shards.iter_mut().zip(coefficients) {
+        for (i, (old, new)) in old_data_words.iter().zip(new_data_words).enumerate() {
+            let delta = (GF232(*new) - GF232(*old)) * coefficient;
+            let offset = position(start + i * 4);
+            let word = &mut shard[offset..offset + 4];
+            word[0] ^= (delta.0 & 0xFF) as u8;
+            word[1] ^= ((delta.0 >> 8) & 0xFF) as u8;
+            word[2] ^= ((delta.0 >> 16) & 0xFF) as u8;
+            word[3] ^= ((delta.0 >> 24) & 0xFF) as u8;
+        }
+        if let Some(ref crc) = crc {
+            for block in blocks.clone() {
+                let (block_start, block_end) = block_range(block, shard.len());
+                let checksum = as_bytes(crc.checksum(&shard[block_start..block_end]));
+                shard[block_end..block_end + checksum_size]
+                    .copy_from_slice(&checksum[..checksum_size]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An error returned when the parity shards can't be updated in place
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UpdateError {
+    /// The shards contain Merkle proofs or are encoded with `Coding::CauchyXor`
+    UnsupportedConfig,
+    /// The index of the data shard is not less than `k`, or the index of a parity
+    /// shard is not in `k..n`
+    InvalidShardIndex(usize),
+    /// A parity shard is too short to contain the updated words
+    InvalidShardLength,
+    /// The checksum of a block of a parity shard which was to be updated is incorrect
+    CorruptedBlock { shard: usize, block: usize },
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UpdateError::UnsupportedConfig => {
+                write!(formatter, "the shards can't be updated in place")
+            }
+            UpdateError::InvalidShardIndex(idx) => write!(formatter, "invalid shard index {}", idx),
+            UpdateError::InvalidShardLength => write!(formatter, "invalid shard length"),
+            UpdateError::CorruptedBlock { shard, block } => {
+                write!(formatter, "block {} of shard {} is corrupted", block, shard)
+            }
+        }
+    }
+}
+
+impl Error for UpdateError {}
+
 /// Struct iterating through multiple iterators simultaneously, returning
 /// their items in a `Vec`
 struct DecodeIter<I: Iterator<Item = u32>> {
@@ -189,19 +725,21 @@ impl<I: Iterator<Item = u32>> Iterator for DecodeIter<I> {
     }
 }
 
-/// Decodes `k` datasets into an array of bytes
-pub fn decode(data: &[(usize, &[u8])], k: usize) -> Vec<u8> {
-    assert!(data.len() >= k);
+/// Decodes `k` datasets into the stream of bytes they encode, including any
+/// length information and padding
+fn decode_stream(data: &[(usize, &[u8])], k: usize) -> Vec<u8> {
+    let indices: Vec<usize> = data.iter().map(|&(idx, _)| idx).collect();
+    let decoder = StripeDecoder::new(&indices, k);
     let decode_iter = DecodeIter::new(
-        data.into_iter()
+        data.iter()
             .map(|&(idx, data)| {
-                (idx, IterU32 { inner: data.into_iter().cloned() })
+                (idx, IterU32 { inner: data.iter().cloned() })
             })
             .collect(),
     );
     let mut result = vec![];
     for stripe in decode_iter {
-        let decoded = decode_stripe(&stripe, k);
+        let decoded = decoder.decode(&stripe);
         for x in decoded {
             result.push((x & 0xFF) as u8);
             result.push(((x >> 8) & 0xFF) as u8);
@@ -209,14 +747,275 @@ pub fn decode(data: &[(usize, &[u8])], k: usize) -> Vec<u8> {
             result.push(((x >> 24) & 0xFF) as u8);
         }
     }
+    result
+}
+
+/// An error returned when the shards can't be decoded
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    /// Fewer than `k` shards were supplied
+    NotEnoughShards { found: usize, required: usize },
+    /// A shard index was not less than `n`
+    InvalidShardIndex(usize),
+    /// More than one shard with the same index was supplied
+    DuplicateShardIndex(usize),
+    /// The shards are of different lengths, or of a length that couldn't have been
+    /// produced by the encoder
+    InvalidShardLength,
+    /// Fewer than `k` of the shards had an intact block with the given index
+    TooManyCorruptedShards { block: usize },
+    /// The coding parameters are invalid
+    InvalidConfig(ConfigError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::NotEnoughShards { found, required } => {
+                write!(
+                    formatter,
+                    "{} shards are required for decoding, found {}",
+                    required,
+                    found
+                )
+            }
+            DecodeError::InvalidShardIndex(idx) => write!(formatter, "invalid shard index {}", idx),
+            DecodeError::DuplicateShardIndex(idx) => {
+                write!(formatter, "duplicate shard index {}", idx)
+            }
+            DecodeError::InvalidShardLength => write!(formatter, "invalid shard length"),
+            DecodeError::TooManyCorruptedShards { block } => {
+                write!(formatter, "too many shards are corrupted in block {}", block)
+            }
+            DecodeError::InvalidConfig(error) => write!(formatter, "{}", error),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Decodes `k` datasets into an array of bytes
+pub fn decode(data: &[(usize, &[u8])], k: usize) -> Vec<u8> {
+    let result = decode_stream(data, k);
     let size = as_u64(&result[0..8]);
     result.into_iter().skip(8).take(size as usize).collect()
 }
 
+/// Splits the data into the `k` data shards created by `encode`, without calculating
+/// any parity: the length is prepended to the data, which is padded to a multiple of
+/// `k` `u32`s, and consecutive `u32`s go to consecutive shards
+pub fn data_shards(data: &[u8], k: usize) -> Vec<Vec<u8>> {
+    let stripe_size = 4 * k;
+    let mut stream = as_bytes(data.len() as u64);
+    stream.extend(data);
+    let padding = stripe_size - stream.len() % stripe_size;
+    let length = stream.len() + padding;
+    stream.resize(length, 0);
+    let mut result = vec![vec![]; k];
+    for (i, word) in stream.chunks(4).enumerate() {
+        result[i % k].extend(word);
+    }
+    result
+}
+
+/// Joins all the data shards created by `encode` back into the data
+pub fn join_data_shards(source: &[Vec<u8>]) -> Result<Vec<u8>, DecodeError> {
+    let length = source.first().map_or(0, |symbol| symbol.len());
+    if length == 0
+        || !length.is_multiple_of(4)
+        || source.iter().any(|symbol| symbol.len() != length)
+    {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    let mut stream = vec![];
+    for pos in (0..length).step_by(4) {
+        for symbol in source {
+            stream.extend(&symbol[pos..pos + 4]);
+        }
+    }
+    unframe(&stream)
+}
+
+/// Decodes shards created by `encode_with_config` with the same configuration into
+/// an array of bytes.
+/// Shards of a different length than most of the others, or of a length which can't
+/// be split into blocks with checksums, are dropped.
+/// If the shards contain Merkle proofs, they are skipped without verification - use
+/// `decode_with_commitment` to verify them. Shards with malformed headers are dropped.
+pub fn decode_with_config(
+    data: &[(usize, &[u8])],
+    config: &CodecConfig,
+) -> Result<Vec<u8>, DecodeError> {
+    decode_shards(data, config, None)
+}
+
+/// Decodes shards created by `encode_with_commitment` with the same configuration into
+/// an array of bytes, ignoring the shards with malformed headers or with proofs that
+/// don't match the given root of the Merkle tree. Returns an error if the
+/// configuration doesn't have the Merkle proofs enabled.
+pub fn decode_with_commitment(
+    data: &[(usize, &[u8])],
+    config: &CodecConfig,
+    root: &Hash,
+) -> Result<Vec<u8>, DecodeError> {
+    if !config.merkle_proofs() {
+        return Err(DecodeError::InvalidConfig(ConfigError::NoMerkleProofs));
+    }
+    decode_shards(data, config, Some(root))
+}
+
+fn decode_shards(
+    data: &[(usize, &[u8])],
+    config: &CodecConfig,
+    root: Option<&Hash>,
+) -> Result<Vec<u8>, DecodeError> {
+    let k = config.k();
+    if data.len() < k {
+        return Err(DecodeError::NotEnoughShards {
+            found: data.len(),
+            required: k,
+        });
+    }
+    check_indices(data, config.n())?;
+    let mut verified = vec![];
+    for &(idx, shard) in data {
+        if !config.merkle_proofs() {
+            verified.push((idx, shard));
+            continue;
+        }
+        let (proof, shard) = match split_merkle_header(shard) {
+            Some(result) => result,
+            None => continue,
+        };
+        let leaf = shard_leaf_hash(idx, shard);
+        match root {
+            Some(root) => {
+                if merkle::root_from_proof(idx, config.n(), leaf, &proof).as_ref() == Some(root) {
+                    verified.push((idx, shard));
+                }
+            }
+            None => verified.push((idx, shard)),
+        }
+    }
+    // the shards of a different length than most of the others are dropped
+    let mut counts = HashMap::new();
+    for &(_, shard) in &verified {
+        *counts.entry(shard.len()).or_insert(0) += 1;
+    }
+    let length = counts
+        .into_iter()
+        .max_by_key(|&(length, count)| (count, length))
+        .map_or(0, |(length, _)| length);
+    verified.retain(|&(_, shard)| shard.len() == length);
+    // the contents of the shards without the checksums, and the intact blocks
+    let mut contents = vec![];
+    let block_size = match checksum_crc(config.checksum()) {
+        Some(crc) => {
+            let block_size = config.checksum_block_size();
+            for &(idx, shard) in &verified {
+                if let Some((shard, intact)) = verify_checksums(shard, &crc, block_size) {
+                    contents.push((idx, shard, intact));
+                }
+            }
+            block_size
+        }
+        None => {
+            for &(idx, shard) in &verified {
+                contents.push((idx, shard.to_vec(), vec![true]));
+            }
+            length + 1
+        }
+    };
+    if contents.len() < k {
+        return Err(DecodeError::NotEnoughShards {
+            found: contents.len(),
+            required: k,
+        });
+    }
+    // returns the shards with all the blocks overlapping the given range intact
+    let intact_shards = |start: usize, end: usize| {
+        contents
+            .iter()
+            .filter(|&(_, _, intact)| {
+                intact[start / block_size..end.div_ceil(block_size)]
+                    .iter()
+                    .all(|x| *x)
+            })
+            .map(|&(idx, ref shard, _)| (idx, &shard[start..end]))
+            .collect::<Vec<_>>()
+    };
+
+    let shard_len = contents[0].1.len();
+    let (body_len, size) = match config.framing() {
+        Framing::Header => (shard_len, None),
+        Framing::Trailer if shard_len >= 8 => {
+            match intact_shards(shard_len - 8, shard_len).first() {
+                Some(&(_, trailer)) => (shard_len - 8, Some(as_u64(trailer))),
+                None => {
+                    return Err(DecodeError::TooManyCorruptedShards {
+                        block: (shard_len - 1) / block_size,
+                    })
+                }
+            }
+        }
+        Framing::Trailer => return Err(DecodeError::InvalidShardLength),
+    };
+    if !body_len.is_multiple_of(4 * config.coding().stripes_per_block()) {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    let mut stream = vec![];
+    let mut cauchy_decoder = cauchy::StreamDecoder::new(config.n(), k);
+    for block in 0..body_len.div_ceil(block_size) {
+        let start = block * block_size;
+        let end = cmp::min(start + block_size, body_len);
+        let bodies = intact_shards(start, end);
+        if bodies.len() < k {
+            return Err(DecodeError::TooManyCorruptedShards { block });
+        }
+        stream.extend(match config.coding() {
+            Coding::Interpolation => decode_stream(&bodies, k),
+            Coding::CauchyXor => cauchy_decoder.decode(&bodies),
+        });
+    }
+    let mut result = reorder(stream, k, config.layout(), true);
+    let size = match size {
+        Some(size) => size,
+        None if result.len() >= 8 => {
+            let size = as_u64(&result[0..8]);
+            result.drain(0..8);
+            size
+        }
+        None => return Err(DecodeError::InvalidShardLength),
+    };
+    if size > result.len() as u64 {
+        return Err(DecodeError::InvalidShardLength);
+    }
+    result.truncate(size as usize);
+    Ok(result)
+}
+
+/// Decodes `k` datasets created by `encode_appendable` into an array of bytes
+pub fn decode_appendable(
+    data: &[(usize, &[u8])],
+    n: usize,
+    k: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    let config = CodecConfig::builder(n, k)
+        .framing(Framing::Trailer)
+        .build()
+        .map_err(DecodeError::InvalidConfig)?;
+    decode_with_config(data, &config)
+}
+
 #[cfg(test)]
 mod test {
     use rand::{self, Rng};
-    use super::{encode, decode};
+    use super::{encode, decode, update_parity, UpdateError};
+    use super::{encode_appendable, append, decode_appendable};
+    use super::{encode_with_config, decode_with_config, verify_shard, DecodeError};
+    use super::{encode_with_commitment, decode_with_commitment};
+    use super::{data_shards, join_data_shards};
+    use config::{Checksum, Coding, CodecConfig, ConfigError, Framing, Layout};
 
     #[test]
     fn test_encode_decode() {
@@ -238,4 +1037,443 @@ mod test {
         );
         assert_eq!(data, decoded);
     }
+
+    #[test]
+    fn test_update_parity() {
+        let mut rng = rand::thread_rng();
+        let (n, k) = (7, 4);
+        let config = CodecConfig::new(n, k).unwrap();
+        let mut data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let mut encoded = encode(&data, n, k);
+
+        let (stripe, data_index) = (10, 2);
+        let new_words: Vec<u32> = rng.gen_iter().take(5).collect();
+        let mut old_words = vec![];
+        for (i, word) in new_words.iter().enumerate() {
+            let offset = (stripe + i) * 4;
+            let shard = &mut encoded[data_index];
+            old_words.push(
+                shard[offset] as u32 | (shard[offset + 1] as u32) << 8 |
+                    (shard[offset + 2] as u32) << 16 |
+                    (shard[offset + 3] as u32) << 24,
+            );
+            for b in 0..4 {
+                let byte = ((word >> (8 * b)) & 0xFF) as u8;
+                shard[offset + b] = byte;
+                data[((stripe + i) * k + data_index) * 4 + b - 8] = byte;
+            }
+        }
+
+        {
+            let mut parity: Vec<(usize, &mut [u8])> = encoded
+                .iter_mut()
+                .enumerate()
+                .skip(k)
+                .map(|(idx, shard)| (idx, &mut shard[..]))
+                .collect();
+            assert_eq!(
+                update_parity(&old_words, &new_words, stripe, data_index, &mut parity, &config),
+                Ok(())
+            );
+        }
+        assert_eq!(encoded, encode(&data, n, k));
+
+        let decoded = decode(
+            &[
+                (1, &encoded[1]),
+                (4, &encoded[4]),
+                (5, &encoded[5]),
+                (6, &encoded[6]),
+            ],
+            k,
+        );
+        assert_eq!(data, decoded);
+
+        let original = encoded.clone();
+        {
+            let mut parity: Vec<(usize, &mut [u8])> = encoded
+                .iter_mut()
+                .enumerate()
+                .skip(k - 1)
+                .map(|(idx, shard)| (idx, &mut shard[..]))
+                .collect();
+            assert_eq!(
+                update_parity(&old_words, &new_words, stripe, data_index, &mut parity, &config),
+                Err(UpdateError::InvalidShardIndex(k - 1))
+            );
+            assert_eq!(
+                update_parity(&old_words, &new_words, stripe, k, &mut parity[1..], &config),
+                Err(UpdateError::InvalidShardIndex(k))
+            );
+            assert_eq!(
+                update_parity(&old_words, &new_words, 1000, data_index, &mut parity[1..], &config),
+                Err(UpdateError::InvalidShardLength)
+            );
+        }
+        assert_eq!(encoded, original);
+    }
+
+    #[test]
+    fn test_update_parity_with_config() {
+        let mut rng = rand::thread_rng();
+        let (n, k) = (7, 4);
+        let config = CodecConfig::builder(n, k)
+            .checksum(Checksum::Crc32c)
+            .checksum_block_size(64)
+            .build()
+            .unwrap();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let mut encoded = encode_with_config(&data, &config);
+
+        // the words of the stripes 14 to 18, which span two checksum blocks
+        let (stripe, data_index) = (14, 1);
+        let new_words: Vec<u32> = rng.gen_iter().take(5).collect();
+        let mut new_data = data.clone();
+        let mut old_words = vec![];
+        for (i, word) in new_words.iter().enumerate() {
+            let offset = ((stripe + i) * k + data_index) * 4 - 8;
+            let mut old_word = 0;
+            for b in 0..4 {
+                old_word |= (new_data[offset + b] as u32) << (8 * b);
+                new_data[offset + b] = ((word >> (8 * b)) & 0xFF) as u8;
+            }
+            old_words.push(old_word);
+        }
+        let expected = encode_with_config(&new_data, &config);
+        {
+            let mut parity: Vec<(usize, &mut [u8])> = encoded
+                .iter_mut()
+                .enumerate()
+                .skip(k)
+                .map(|(idx, shard)| (idx, &mut shard[..]))
+                .collect();
+            assert_eq!(
+                update_parity(&old_words, &new_words, stripe, data_index, &mut parity, &config),
+                Ok(())
+            );
+        }
+        assert_eq!(&encoded[k..], &expected[k..]);
+
+        // a corrupted block isn't given a valid checksum
+        encoded[5][70] ^= 1;
+        let original = encoded.clone();
+        {
+            let mut parity: Vec<(usize, &mut [u8])> = encoded
+                .iter_mut()
+                .enumerate()
+                .skip(k)
+                .map(|(idx, shard)| (idx, &mut shard[..]))
+                .collect();
+            assert_eq!(
+                update_parity(&new_words, &old_words, stripe, data_index, &mut parity, &config),
+                Err(UpdateError::CorruptedBlock { shard: 5, block: 1 })
+            );
+            let unsupported = [(Coding::CauchyXor, false), (Coding::Interpolation, true)];
+            for &(coding, merkle_proofs) in &unsupported {
+                let config = CodecConfig::builder(n, k)
+                    .coding(coding)
+                    .merkle_proofs(merkle_proofs)
+                    .build()
+                    .unwrap();
+                assert_eq!(
+                    update_parity(&new_words, &old_words, stripe, data_index, &mut parity, &config),
+                    Err(UpdateError::UnsupportedConfig)
+                );
+            }
+        }
+        assert_eq!(encoded, original);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut rng = rand::thread_rng();
+        let (n, k) = (6, 3);
+        let data: Vec<u8> = rng.gen_iter().take(1001).collect();
+        let mut encoded = encode_appendable(&data[..37], n, k);
+        assert_eq!(encoded[0].len(), 4 * 4 + 8);
+        let decoded = decode_appendable(
+            &[(0, &encoded[0]), (4, &encoded[4]), (5, &encoded[5])],
+            n,
+            k,
+        );
+        assert_eq!(Ok(data[..37].to_vec()), decoded);
+
+        assert_eq!(append(&mut encoded, &data[37..48], k), Ok(()));
+        assert_eq!(append(&mut encoded, &[], k), Ok(()));
+        assert_eq!(append(&mut encoded, &data[48..], k), Ok(()));
+        assert_eq!(encoded, encode_appendable(&data, n, k));
+
+        let decoded = decode_appendable(
+            &[(5, &encoded[5]), (1, &encoded[1]), (3, &encoded[3])],
+            n,
+            k,
+        );
+        assert_eq!(Ok(data.clone()), decoded);
+
+        // malformed shards are rejected without being modified
+        let mut short = vec![vec![0; 5]; n];
+        assert_eq!(append(&mut short, &[1], k), Err(DecodeError::InvalidShardLength));
+        assert_eq!(short, vec![vec![0; 5]; n]);
+        let mut uneven = encode_appendable(&data[..37], n, k);
+        uneven[2].pop();
+        assert_eq!(append(&mut uneven, &[1], k), Err(DecodeError::InvalidShardLength));
+        let mut wrong_length = encode_appendable(&data[..37], n, k);
+        for shard in &mut wrong_length {
+            let trailer_start = shard.len() - 8;
+            shard[trailer_start] = 100;
+        }
+        assert_eq!(
+            append(&mut wrong_length, &[1], k),
+            Err(DecodeError::InvalidShardLength)
+        );
+        assert_eq!(
+            append(&mut encoded[..2], &[1], k),
+            Err(DecodeError::NotEnoughShards { found: 2, required: 3 })
+        );
+        assert_eq!(
+            append(&mut encoded, &[1], 0),
+            Err(DecodeError::InvalidConfig(ConfigError::NoDataShards))
+        );
+        assert_eq!(
+            append(&mut [], &[1], 0),
+            Err(DecodeError::InvalidConfig(ConfigError::NoDataShards))
+        );
+        assert_eq!(
+            decode_appendable(&[(0, &encoded[0])], n, 0),
+            Err(DecodeError::InvalidConfig(ConfigError::NoDataShards))
+        );
+    }
+
+    #[test]
+    fn test_block_layout() {
+        let mut rng = rand::thread_rng();
+        let (n, k) = (7, 4);
+        let data: Vec<u8> = rng.gen_iter().take(3000).collect();
+        let config = CodecConfig::builder(n, k)
+            .layout(Layout::Blocks)
+            .build()
+            .unwrap();
+        let encoded = encode_with_config(&data, &config);
+
+        let chunk = encoded[0].len();
+        assert_eq!(chunk % 4, 0);
+        assert!(chunk * k >= 8 + data.len());
+        assert_eq!(&encoded[0][8..], &data[..chunk - 8]);
+        assert_eq!(&encoded[1][..], &data[chunk - 8..2 * chunk - 8]);
+
+        let decoded = decode_with_config(
+            &[
+                (6, &encoded[6]),
+                (2, &encoded[2]),
+                (4, &encoded[4]),
+                (5, &encoded[5]),
+            ],
+            &config,
+        );
+        assert_eq!(Ok(data), decoded);
+    }
+
+    #[test]
+    fn test_decode_with_config() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(500).collect();
+        let config = CodecConfig::builder(5, 3)
+            .framing(Framing::Trailer)
+            .layout(Layout::Blocks)
+            .build()
+            .unwrap();
+        let encoded = encode_with_config(&data, &config);
+
+        let decoded = decode_with_config(
+            &[(4, &encoded[4]), (0, &encoded[0]), (2, &encoded[2])],
+            &config,
+        );
+        assert_eq!(decoded, Ok(data.clone()));
+        assert_eq!(
+            decode_with_config(&[(4, &encoded[4]), (0, &encoded[0])], &config),
+            Err(DecodeError::NotEnoughShards { found: 2, required: 3 })
+        );
+        assert_eq!(
+            decode_with_config(
+                &[(4, &encoded[4]), (0, &encoded[0]), (5, &encoded[0])],
+                &config,
+            ),
+            Err(DecodeError::InvalidShardIndex(5))
+        );
+        assert_eq!(
+            decode_with_config(
+                &[(4, &encoded[4]), (0, &encoded[0]), (4, &encoded[4])],
+                &config,
+            ),
+            Err(DecodeError::DuplicateShardIndex(4))
+        );
+        // shards of a different length than the others are dropped
+        assert_eq!(
+            decode_with_config(
+                &[(4, &encoded[4]), (0, &encoded[0]), (1, &encoded[1][1..])],
+                &config,
+            ),
+            Err(DecodeError::NotEnoughShards { found: 2, required: 3 })
+        );
+        assert_eq!(
+            decode_with_config(
+                &[(4, &encoded[4]), (1, &encoded[1][1..]), (0, &encoded[0]), (3, &encoded[3])],
+                &config,
+            ),
+            Ok(data)
+        );
+    }
+
+    #[test]
+    fn test_cauchy_coding() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(3000).collect();
+        let config = CodecConfig::builder(6, 3)
+            .coding(Coding::CauchyXor)
+            .checksum(Checksum::Crc32c)
+            .checksum_block_size(256)
+            .build()
+            .unwrap();
+        let mut encoded = encode_with_config(&data, &config);
+
+        // the data shards are the same as with interpolation, apart from the padding to
+        // whole blocks of 32 stripes
+        let cauchy = CodecConfig::builder(6, 3).coding(Coding::CauchyXor).build().unwrap();
+        let plain = encode(&data, 6, 3);
+        for (j, shard) in encode_with_config(&data, &cauchy).iter().enumerate() {
+            assert_eq!(shard.len(), 128 * 8);
+            assert_eq!(shard.starts_with(&plain[j]), j < 3);
+        }
+
+        // corrupt different blocks of three shards
+        for (idx, pos) in [(0, 10), (1, 300), (4, 600)].iter().cloned() {
+            encoded[idx][pos] ^= 1;
+        }
+        let shards: Vec<(usize, &[u8])> = encoded
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| (idx, &shard[..]))
+            .collect();
+        assert_eq!(decode_with_config(&shards, &config), Ok(data.clone()));
+        assert_eq!(decode_with_config(&shards[2..6], &config), Ok(data));
+    }
+
+    #[test]
+    fn test_checksums() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        for &(checksum, framing) in &[
+            (Checksum::Crc32c, Framing::Header),
+            (Checksum::Crc64, Framing::Trailer),
+        ]
+        {
+            let config = CodecConfig::builder(6, 3)
+                .checksum(checksum)
+                .checksum_block_size(64)
+                .framing(framing)
+                .build()
+                .unwrap();
+            let mut encoded = encode_with_config(&data, &config);
+            assert!(encoded.iter().all(|shard| verify_shard(shard, &config)));
+
+            // corrupt a different block in each of three shards
+            encoded[0][5] ^= 1;
+            encoded[2][100] ^= 0x80;
+            let last = encoded[4].len() - 1;
+            encoded[4][last - 9] ^= 0x10;
+            assert!(!verify_shard(&encoded[0], &config));
+            assert!(!verify_shard(&encoded[2], &config));
+            assert!(!verify_shard(&encoded[4], &config));
+
+            let shards: Vec<(usize, &[u8])> = encoded
+                .iter()
+                .enumerate()
+                .map(|(idx, shard)| (idx, &shard[..]))
+                .collect();
+            assert_eq!(decode_with_config(&shards, &config), Ok(data.clone()));
+            // a truncated shard is dropped
+            let truncated: Vec<(usize, &[u8])> = shards
+                .iter()
+                .map(|&(idx, shard)| (idx, if idx == 1 { &shard[..65] } else { shard }))
+                .collect();
+            assert_eq!(decode_with_config(&truncated, &config), Ok(data.clone()));
+
+            // only two shards have an intact first block
+            encoded[1][0] ^= 1;
+            encoded[3][1] ^= 1;
+            encoded[5][2] ^= 1;
+            let shards: Vec<(usize, &[u8])> = encoded
+                .iter()
+                .enumerate()
+                .map(|(idx, shard)| (idx, &shard[..]))
+                .collect();
+            assert_eq!(
+                decode_with_config(&shards, &config),
+                Err(DecodeError::TooManyCorruptedShards { block: 0 })
+            );
+        }
+    }
+
+    #[test]
+    fn test_merkle_proofs() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(300).collect();
+        let config = CodecConfig::builder(5, 2)
+            .merkle_proofs(true)
+            .checksum(Checksum::Crc32c)
+            .build()
+            .unwrap();
+        let (mut encoded, root) = encode_with_commitment(&data, &config).unwrap();
+        assert_eq!(encoded, encode_with_config(&data, &config));
+        let plain = CodecConfig::new(5, 2).unwrap();
+        assert_eq!(encode_with_commitment(&data, &plain), Err(ConfigError::NoMerkleProofs));
+        assert_eq!(
+            decode_with_commitment(&[], &plain, &root),
+            Err(DecodeError::InvalidConfig(ConfigError::NoMerkleProofs))
+        );
+
+        // shard 4 is in a different position in the tree, so its proof is shorter
+        assert!(encoded[4].len() < encoded[0].len());
+        let shards: Vec<(usize, &[u8])> = encoded
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| (idx, &shard[..]))
+            .collect();
+        assert_eq!(decode_with_commitment(&shards, &config, &root), Ok(data.clone()));
+        assert_eq!(decode_with_config(&shards[3..], &config), Ok(data.clone()));
+
+        // replace shard 0 with a shard of different data (with intact checksums), and
+        // shard 3 with a copy of shard 4
+        let other: Vec<u8> = rng.gen_iter().take(300).collect();
+        encoded[0] = encode_with_config(&other, &config)[0].clone();
+        encoded[3] = encoded[4].clone();
+        let shards: Vec<(usize, &[u8])> = encoded
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| (idx, &shard[..]))
+            .collect();
+        assert_eq!(decode_with_commitment(&shards, &config, &root), Ok(data.clone()));
+        // a shard with a malformed header is skipped
+        assert_eq!(
+            decode_with_commitment(&[(0, &[1, 2][..]), shards[4], shards[1]], &config, &root),
+            Ok(data)
+        );
+        assert_eq!(
+            decode_with_commitment(&[shards[0], shards[3], shards[1]], &config, &root),
+            Err(DecodeError::NotEnoughShards { found: 1, required: 2 })
+        );
+    }
+
+    #[test]
+    fn test_join_data_shards() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut shards = data_shards(&data, 3);
+        assert_eq!(join_data_shards(&shards), Ok(data));
+
+        assert_eq!(join_data_shards(&[]), Err(DecodeError::InvalidShardLength));
+        shards[1].pop();
+        assert_eq!(join_data_shards(&shards), Err(DecodeError::InvalidShardLength));
+        shards[1].truncate(4);
+        assert_eq!(join_data_shards(&shards), Err(DecodeError::InvalidShardLength));
+    }
 }
@@ -0,0 +1,482 @@
+use codec::{as_bytes, as_u64};
+use std::error::Error;
+use std::fmt;
+
+/// The maximum number of shards - every shard corresponds to a different element
+/// of the field, so there can't be more of them than `2^32`
+pub const MAX_SHARDS: u64 = 1 << 32;
+
+/// The version of the format produced by `CodecConfig::to_bytes`. Every version
+/// appends new options to the format of the previous one: version 2 added the
+/// checksum block size, version 3 the Merkle proofs flag and version 4 the coding.
+const CONFIG_VERSION: u8 = 4;
+
+/// The lengths of the formats of the successive versions, starting with version 1
+const CONFIG_LENGTHS: [usize; 4] = [21, 29, 30, 31];
+
+/// The default number of bytes of a shard protected by a single checksum
+pub const DEFAULT_CHECKSUM_BLOCK_SIZE: usize = 4096;
+
+/// The Galois field in which the coding is performed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    GF232,
+}
+
+/// The way the data is distributed among the data shards
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layout {
+    /// Consecutive `u32`s go to consecutive shards, so that every stripe is made of
+    /// `k` consecutive `u32`s of the data
+    Interleaved,
+    /// Every data shard contains a contiguous `1/k` of the data, and the `i`-th stripe
+    /// is made of the `i`-th `u32`s of all the chunks
+    Blocks,
+}
+
+/// The way the length of the data is stored in the shards
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Framing {
+    /// The length is prepended to the data as 8 little-endian bytes, and the data is
+    /// padded to a multiple of `k` `u32`s (the format used by `encode`)
+    Header,
+    /// The length is stored in an 8-byte trailer at the end of every shard (the
+    /// format used by `encode_appendable`)
+    Trailer,
+}
+
+/// The algorithm calculating the parity shards
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Coding {
+    /// Lagrange interpolation of every stripe over the points `0..k`, evaluated at the
+    /// points `k..n` (the code used by `encode`)
+    Interpolation,
+    /// A Cauchy Reed-Solomon code with the coefficients expanded into 32x32 binary
+    /// matrices, so that the parity is calculated using only XORs. The data is encoded
+    /// in blocks of 32 stripes, so the checksum block size has to be a multiple of
+    /// 128 bytes.
+    CauchyXor,
+}
+
+impl Coding {
+    /// The number of stripes encoded together
+    pub fn stripes_per_block(&self) -> usize {
+        match *self {
+            Coding::Interpolation => 1,
+            Coding::CauchyXor => 32,
+        }
+    }
+}
+
+/// The checksum algorithm protecting the contents of the shards. Every block of
+/// a shard is followed by its checksum, stored as little-endian bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Checksum {
+    None,
+    /// CRC-32C (Castagnoli), 4 bytes per block
+    Crc32c,
+    /// CRC-64/XZ, 8 bytes per block
+    Crc64,
+}
+
+impl Checksum {
+    /// The number of bytes taken by a single checksum
+    pub fn size(&self) -> usize {
+        match *self {
+            Checksum::None => 0,
+            Checksum::Crc32c => 4,
+            Checksum::Crc64 => 8,
+        }
+    }
+}
+
+/// An error returned when the coding parameters are invalid
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigError {
+    /// `k` is 0
+    NoDataShards,
+    /// `k` is larger than `n`
+    NotEnoughShards { n: usize, k: usize },
+    /// `n` is larger than `MAX_SHARDS`
+    TooManyShards(usize),
+    /// The checksum block size is 0 or not a multiple of 4 (or of 128 with
+    /// `Coding::CauchyXor`)
+    InvalidChecksumBlockSize(usize),
+    /// The serialized configuration couldn't be parsed
+    InvalidEncoding,
+    /// The Merkle proofs are required, but not enabled in the configuration
+    NoMerkleProofs,
+    /// The parameters aren't supported by a code, for the given reason
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::NoDataShards => write!(formatter, "the number of data shards is 0"),
+            ConfigError::NotEnoughShards { n, k } => {
+                write!(
+                    formatter,
+                    "the number of data shards ({}) exceeds the number of shards ({})",
+                    k,
+                    n
+                )
+            }
+            ConfigError::TooManyShards(n) => {
+                write!(
+                    formatter,
+                    "the number of shards ({}) exceeds the maximum of {}",
+                    n,
+                    MAX_SHARDS
+                )
+            }
+            ConfigError::InvalidChecksumBlockSize(size) => {
+                write!(formatter, "invalid checksum block size {}", size)
+            }
+            ConfigError::InvalidEncoding => write!(formatter, "invalid serialized configuration"),
+            ConfigError::NoMerkleProofs => write!(formatter, "the Merkle proofs are not enabled"),
+            ConfigError::Unsupported(reason) => write!(formatter, "{}", reason),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// A validated set of parameters used for both encoding and decoding: the data is
+/// encoded into `n` shards, any `k` of which are sufficient to decode it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CodecConfig {
+    n: usize,
+    k: usize,
+    field: Field,
+    layout: Layout,
+    framing: Framing,
+    checksum: Checksum,
+    checksum_block_size: usize,
+    merkle_proofs: bool,
+    coding: Coding,
+}
+
+impl CodecConfig {
+    /// Creates a configuration with the default options
+    pub fn new(n: usize, k: usize) -> Result<CodecConfig, ConfigError> {
+        CodecConfig::builder(n, k).build()
+    }
+
+    /// Returns a builder for a configuration, initialised with the default options
+    pub fn builder(n: usize, k: usize) -> CodecConfigBuilder {
+        CodecConfigBuilder::new(n, k)
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    pub fn checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    /// The number of bytes of a shard protected by a single checksum
+    pub fn checksum_block_size(&self) -> usize {
+        self.checksum_block_size
+    }
+
+    /// Whether every shard starts with a header containing a proof of its inclusion
+    /// in a Merkle tree over all the shards
+    pub fn merkle_proofs(&self) -> bool {
+        self.merkle_proofs
+    }
+
+    pub fn coding(&self) -> Coding {
+        self.coding
+    }
+
+    /// Serializes the configuration, so that it can be stored alongside the shards
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = vec![CONFIG_VERSION];
+        result.extend(as_bytes(self.n as u64));
+        result.extend(as_bytes(self.k as u64));
+        result.push(match self.field {
+            Field::GF232 => 0,
+        });
+        result.push(match self.layout {
+            Layout::Interleaved => 0,
+            Layout::Blocks => 1,
+        });
+        result.push(match self.framing {
+            Framing::Header => 0,
+            Framing::Trailer => 1,
+        });
+        result.push(match self.checksum {
+            Checksum::None => 0,
+            Checksum::Crc32c => 1,
+            Checksum::Crc64 => 2,
+        });
+        result.extend(as_bytes(self.checksum_block_size as u64));
+        result.push(self.merkle_proofs as u8);
+        result.push(match self.coding {
+            Coding::Interpolation => 0,
+            Coding::CauchyXor => 1,
+        });
+        result
+    }
+
+    /// Deserializes and validates a configuration created by `to_bytes`. The options
+    /// missing from the formats of the earlier versions take their default values.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CodecConfig, ConfigError> {
+        let version = match bytes.first() {
+            Some(&version) if (1..=CONFIG_VERSION).contains(&version) => version,
+            _ => return Err(ConfigError::InvalidEncoding),
+        };
+        if bytes.len() != CONFIG_LENGTHS[version as usize - 1] {
+            return Err(ConfigError::InvalidEncoding);
+        }
+        let n = as_u64(&bytes[1..9]);
+        let k = as_u64(&bytes[9..17]);
+        if n > MAX_SHARDS || k > n {
+            return Err(ConfigError::InvalidEncoding);
+        }
+        let field = match bytes[17] {
+            0 => Field::GF232,
+            _ => return Err(ConfigError::InvalidEncoding),
+        };
+        let layout = match bytes[18] {
+            0 => Layout::Interleaved,
+            1 => Layout::Blocks,
+            _ => return Err(ConfigError::InvalidEncoding),
+        };
+        let framing = match bytes[19] {
+            0 => Framing::Header,
+            1 => Framing::Trailer,
+            _ => return Err(ConfigError::InvalidEncoding),
+        };
+        let checksum = match bytes[20] {
+            0 => Checksum::None,
+            1 => Checksum::Crc32c,
+            2 => Checksum::Crc64,
+            _ => return Err(ConfigError::InvalidEncoding),
+        };
+        let mut builder = CodecConfig::builder(n as usize, k as usize)
+            .field(field)
+            .layout(layout)
+            .framing(framing)
+            .checksum(checksum);
+        if version >= 2 {
+            let checksum_block_size = as_u64(&bytes[21..29]);
+            if checksum_block_size > usize::MAX as u64 {
+                return Err(ConfigError::InvalidEncoding);
+            }
+            builder = builder.checksum_block_size(checksum_block_size as usize);
+        }
+        if version >= 3 {
+            builder = builder.merkle_proofs(match bytes[29] {
+                0 => false,
+                1 => true,
+                _ => return Err(ConfigError::InvalidEncoding),
+            });
+        }
+        if version >= 4 {
+            builder = builder.coding(match bytes[30] {
+                0 => Coding::Interpolation,
+                1 => Coding::CauchyXor,
+                _ => return Err(ConfigError::InvalidEncoding),
+            });
+        }
+        builder.build()
+    }
+}
+
+/// Builder for `CodecConfig`, validating the parameters in `build`
+#[derive(Clone, Copy, Debug)]
+pub struct CodecConfigBuilder {
+    config: CodecConfig,
+}
+
+impl CodecConfigBuilder {
+    pub fn new(n: usize, k: usize) -> CodecConfigBuilder {
+        CodecConfigBuilder {
+            config: CodecConfig {
+                n,
+                k,
+                field: Field::GF232,
+                layout: Layout::Interleaved,
+                framing: Framing::Header,
+                checksum: Checksum::None,
+                checksum_block_size: DEFAULT_CHECKSUM_BLOCK_SIZE,
+                merkle_proofs: false,
+                coding: Coding::Interpolation,
+            },
+        }
+    }
+
+    pub fn field(mut self, field: Field) -> CodecConfigBuilder {
+        self.config.field = field;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> CodecConfigBuilder {
+        self.config.layout = layout;
+        self
+    }
+
+    pub fn framing(mut self, framing: Framing) -> CodecConfigBuilder {
+        self.config.framing = framing;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: Checksum) -> CodecConfigBuilder {
+        self.config.checksum = checksum;
+        self
+    }
+
+    /// Sets the number of bytes of a shard protected by a single checksum, which has
+    /// to be a multiple of 4
+    pub fn checksum_block_size(mut self, size: usize) -> CodecConfigBuilder {
+        self.config.checksum_block_size = size;
+        self
+    }
+
+    /// Enables or disables the Merkle proofs in the shard headers
+    pub fn merkle_proofs(mut self, enabled: bool) -> CodecConfigBuilder {
+        self.config.merkle_proofs = enabled;
+        self
+    }
+
+    /// Sets the algorithm calculating the parity shards
+    pub fn coding(mut self, coding: Coding) -> CodecConfigBuilder {
+        self.config.coding = coding;
+        self
+    }
+
+    /// Validates the parameters and returns the configuration
+    pub fn build(self) -> Result<CodecConfig, ConfigError> {
+        let CodecConfig {
+            n,
+            k,
+            checksum_block_size,
+            coding,
+            ..
+        } = self.config;
+        if k == 0 {
+            return Err(ConfigError::NoDataShards);
+        }
+        if k > n {
+            return Err(ConfigError::NotEnoughShards { n, k });
+        }
+        if n as u64 > MAX_SHARDS {
+            return Err(ConfigError::TooManyShards(n));
+        }
+        if checksum_block_size == 0 ||
+            !checksum_block_size.is_multiple_of(4 * coding.stripes_per_block())
+        {
+            return Err(ConfigError::InvalidChecksumBlockSize(checksum_block_size));
+        }
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Checksum, Coding, CodecConfig, ConfigError, Framing, Layout};
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(CodecConfig::new(5, 0), Err(ConfigError::NoDataShards));
+        assert_eq!(
+            CodecConfig::new(3, 4),
+            Err(ConfigError::NotEnoughShards { n: 3, k: 4 })
+        );
+        assert!(CodecConfig::new(4, 4).is_ok());
+        assert!(CodecConfig::new(10, 1).is_ok());
+        assert_eq!(
+            CodecConfig::builder(5, 3).checksum_block_size(6).build(),
+            Err(ConfigError::InvalidChecksumBlockSize(6))
+        );
+        assert_eq!(
+            CodecConfig::builder(5, 3)
+                .coding(Coding::CauchyXor)
+                .checksum_block_size(1000)
+                .build(),
+            Err(ConfigError::InvalidChecksumBlockSize(1000))
+        );
+    }
+
+    #[test]
+    fn test_serialization() {
+        let config = CodecConfig::builder(12, 7)
+            .layout(Layout::Blocks)
+            .framing(Framing::Trailer)
+            .checksum(Checksum::Crc64)
+            .checksum_block_size(1024)
+            .merkle_proofs(true)
+            .coding(Coding::CauchyXor)
+            .build()
+            .unwrap();
+        let bytes = config.to_bytes();
+        assert_eq!(CodecConfig::from_bytes(&bytes), Ok(config));
+
+        // version 1, without the checksum block size
+        let mut old = bytes[..21].to_vec();
+        old[0] = 1;
+        let expected = CodecConfig::builder(12, 7)
+            .layout(Layout::Blocks)
+            .framing(Framing::Trailer)
+            .checksum(Checksum::Crc64)
+            .build();
+        assert_eq!(CodecConfig::from_bytes(&old), expected);
+        // version 2, without the Merkle proofs flag
+        let mut old = bytes[..29].to_vec();
+        old[0] = 2;
+        let expected = CodecConfig::builder(12, 7)
+            .layout(Layout::Blocks)
+            .framing(Framing::Trailer)
+            .checksum(Checksum::Crc64)
+            .checksum_block_size(1024)
+            .build();
+        assert_eq!(CodecConfig::from_bytes(&old), expected);
+        // version 3, without the coding
+        let mut old = bytes[..30].to_vec();
+        old[0] = 3;
+        let expected = CodecConfig::builder(12, 7)
+            .layout(Layout::Blocks)
+            .framing(Framing::Trailer)
+            .checksum(Checksum::Crc64)
+            .checksum_block_size(1024)
+            .merkle_proofs(true)
+            .build();
+        assert_eq!(CodecConfig::from_bytes(&old), expected);
+        let mut invalid = bytes.clone();
+        invalid[0] = 1;
+        assert_eq!(
+            CodecConfig::from_bytes(&invalid),
+            Err(ConfigError::InvalidEncoding)
+        );
+        assert_eq!(
+            CodecConfig::from_bytes(&bytes[1..]),
+            Err(ConfigError::InvalidEncoding)
+        );
+
+        let mut invalid = bytes.clone();
+        invalid[9] = 13;
+        assert_eq!(
+            CodecConfig::from_bytes(&invalid),
+            Err(ConfigError::InvalidEncoding)
+        );
+    }
+}
@@ -0,0 +1,457 @@
+use polynomial::BinaryPolynomial;
+
+/// Parameters of a CRC in the Rocksoft model (as used by the catalogue of parametrised
+/// CRC algorithms)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CrcParams {
+    pub name: &'static str,
+    /// The width of the checksum in bits, between 1 and 64
+    pub width: u8,
+    /// The generator polynomial with the `X^width` term omitted, in the normal
+    /// (not reflected) representation
+    pub poly: BinaryPolynomial,
+    /// The initial value of the register, in the normal representation
+    pub init: u64,
+    /// Whether the bits of the input bytes are processed from the least significant one
+    pub refin: bool,
+    /// Whether the final value of the register is reflected before the final XOR
+    pub refout: bool,
+    /// The value XORed with the final value of the register
+    pub xorout: u64,
+    /// The checksum of the ASCII string "123456789"
+    pub check: u64,
+}
+
+macro_rules! crc_params {
+    ($konst:ident, $name:expr, $width:expr, $poly:expr, $init:expr, $refin:expr, $refout:expr,
+     $xorout:expr, $check:expr) => {
+        pub const $konst: CrcParams = CrcParams {
+            name: $name,
+            width: $width,
+            poly: BinaryPolynomial($poly),
+            init: $init,
+            refin: $refin,
+            refout: $refout,
+            xorout: $xorout,
+            check: $check,
+        };
+    };
+}
+
+crc_params!(CRC_3_ROHC, "CRC-3/ROHC", 3, 0x3, 0x7, true, true, 0x0, 0x6);
+crc_params!(CRC_5_USB, "CRC-5/USB", 5, 0x05, 0x1F, true, true, 0x1F, 0x19);
+crc_params!(CRC_7_MMC, "CRC-7/MMC", 7, 0x09, 0x0, false, false, 0x0, 0x75);
+crc_params!(CRC_8_SMBUS, "CRC-8/SMBUS", 8, 0x07, 0x0, false, false, 0x0, 0xF4);
+crc_params!(CRC_8_MAXIM_DOW, "CRC-8/MAXIM-DOW", 8, 0x31, 0x0, true, true, 0x0, 0xA1);
+crc_params!(CRC_10_ATM, "CRC-10/ATM", 10, 0x233, 0x0, false, false, 0x0, 0x199);
+crc_params!(CRC_12_UMTS, "CRC-12/UMTS", 12, 0x80F, 0x0, false, true, 0x0, 0xDAF);
+crc_params!(CRC_15_CAN, "CRC-15/CAN", 15, 0x4599, 0x0, false, false, 0x0, 0x059E);
+crc_params!(CRC_16_ARC, "CRC-16/ARC", 16, 0x8005, 0x0, true, true, 0x0, 0xBB3D);
+crc_params!(
+    CRC_16_IBM_3740,
+    "CRC-16/IBM-3740",
+    16,
+    0x1021,
+    0xFFFF,
+    false,
+    false,
+    0x0,
+    0x29B1
+);
+crc_params!(CRC_16_KERMIT, "CRC-16/KERMIT", 16, 0x1021, 0x0, true, true, 0x0, 0x2189);
+crc_params!(CRC_16_XMODEM, "CRC-16/XMODEM", 16, 0x1021, 0x0, false, false, 0x0, 0x31C3);
+crc_params!(CRC_16_MODBUS, "CRC-16/MODBUS", 16, 0x8005, 0xFFFF, true, true, 0x0, 0x4B37);
+crc_params!(CRC_16_USB, "CRC-16/USB", 16, 0x8005, 0xFFFF, true, true, 0xFFFF, 0xB4C8);
+crc_params!(
+    CRC_24_OPENPGP,
+    "CRC-24/OPENPGP",
+    24,
+    0x86_4CFB,
+    0xB7_04CE,
+    false,
+    false,
+    0x0,
+    0x21_CF02
+);
+crc_params!(
+    CRC_32_ISO_HDLC,
+    "CRC-32/ISO-HDLC",
+    32,
+    0x04C1_1DB7,
+    0xFFFF_FFFF,
+    true,
+    true,
+    0xFFFF_FFFF,
+    0xCBF4_3926
+);
+crc_params!(
+    CRC_32_BZIP2,
+    "CRC-32/BZIP2",
+    32,
+    0x04C1_1DB7,
+    0xFFFF_FFFF,
+    false,
+    false,
+    0xFFFF_FFFF,
+    0xFC89_1918
+);
+crc_params!(
+    CRC_32_MPEG_2,
+    "CRC-32/MPEG-2",
+    32,
+    0x04C1_1DB7,
+    0xFFFF_FFFF,
+    false,
+    false,
+    0x0,
+    0x0376_E6E7
+);
+crc_params!(
+    CRC_32_CKSUM,
+    "CRC-32/CKSUM",
+    32,
+    0x04C1_1DB7,
+    0x0,
+    false,
+    false,
+    0xFFFF_FFFF,
+    0x765E_7680
+);
+crc_params!(
+    CRC_32_ISCSI,
+    "CRC-32/ISCSI",
+    32,
+    0x1EDC_6F41,
+    0xFFFF_FFFF,
+    true,
+    true,
+    0xFFFF_FFFF,
+    0xE306_9283
+);
+crc_params!(
+    CRC_40_GSM,
+    "CRC-40/GSM",
+    40,
+    0x00_0482_0009,
+    0x0,
+    false,
+    false,
+    0xFF_FFFF_FFFF,
+    0xD4_164F_C646
+);
+crc_params!(
+    CRC_64_ECMA_182,
+    "CRC-64/ECMA-182",
+    64,
+    0x42F0_E1EB_A9EA_3693,
+    0x0,
+    false,
+    false,
+    0x0,
+    0x6C40_DF5F_0B49_7347
+);
+crc_params!(
+    CRC_64_GO_ISO,
+    "CRC-64/GO-ISO",
+    64,
+    0x1B,
+    0xFFFF_FFFF_FFFF_FFFF,
+    true,
+    true,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xB909_56C7_75A4_1001
+);
+crc_params!(
+    CRC_64_XZ,
+    "CRC-64/XZ",
+    64,
+    0x42F0_E1EB_A9EA_3693,
+    0xFFFF_FFFF_FFFF_FFFF,
+    true,
+    true,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0x995D_C9BB_DF19_39FA
+);
+
+/// All the CRCs with predefined parameters
+pub const CATALOGUE: &[CrcParams] = &[
+    CRC_3_ROHC,
+    CRC_5_USB,
+    CRC_7_MMC,
+    CRC_8_SMBUS,
+    CRC_8_MAXIM_DOW,
+    CRC_10_ATM,
+    CRC_12_UMTS,
+    CRC_15_CAN,
+    CRC_16_ARC,
+    CRC_16_IBM_3740,
+    CRC_16_KERMIT,
+    CRC_16_XMODEM,
+    CRC_16_MODBUS,
+    CRC_16_USB,
+    CRC_24_OPENPGP,
+    CRC_32_ISO_HDLC,
+    CRC_32_BZIP2,
+    CRC_32_MPEG_2,
+    CRC_32_CKSUM,
+    CRC_32_ISCSI,
+    CRC_40_GSM,
+    CRC_64_ECMA_182,
+    CRC_64_GO_ISO,
+    CRC_64_XZ,
+];
+
+/// A table-driven implementation of a CRC with arbitrary Rocksoft model parameters,
+/// processing 8 bytes at a time (slicing-by-8).
+/// The tables are computed using `BinaryPolynomial` arithmetic modulo the generator.
+pub struct Crc {
+    params: CrcParams,
+    tables: Vec<[u64; 256]>,
+}
+
+/// Returns a mask of the lowest `width` bits
+fn mask(width: u8) -> u64 {
+    if width == 64 {
+        !0
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// Reverses the order of the lowest `width` bits of `x`
+fn reflect(x: u64, width: u8) -> u64 {
+    let mut result = 0;
+    for i in 0..width {
+        if x & (1 << i) != 0 {
+            result |= 1 << (width - 1 - i);
+        }
+    }
+    result
+}
+
+/// Returns `x * X mod G`, where `G = X^width + low` and `x` is of a degree lower than
+/// `width` (this way `G` doesn't have to fit in a `BinaryPolynomial`)
+fn mul_x_mod(x: BinaryPolynomial, low: BinaryPolynomial, width: u8) -> BinaryPolynomial {
+    let overflow = x.degree() == width as i8 - 1;
+    let shifted = BinaryPolynomial((x.0 << 1) & mask(width));
+    if overflow { shifted + low } else { shifted }
+}
+
+impl Crc {
+    /// Creates a CRC with the given parameters
+    pub fn new(params: CrcParams) -> Crc {
+        let width = params.width;
+        assert!((1..65).contains(&width));
+        let mut table = [0; 256];
+        for (byte, entry) in table.iter_mut().enumerate() {
+            // the remainder of (byte * X^width) mod G, feeding the bits of the byte
+            // into the register one by one, most significant first
+            let mut x = BinaryPolynomial(0);
+            for bit in (0..8).rev() {
+                x = mul_x_mod(x, params.poly, width);
+                if byte & (1 << bit) != 0 {
+                    x += params.poly;
+                }
+            }
+            // reflected CRCs keep the register in the lowest bits with reversed bit
+            // order, the other ones in the highest bits
+            *entry = if params.refin {
+                reflect(x.0, width)
+            } else {
+                x.0 << (64 - width)
+            };
+        }
+        if params.refin {
+            let normal = table;
+            for (byte, entry) in table.iter_mut().enumerate() {
+                *entry = normal[reflect(byte as u64, 8) as usize];
+            }
+        }
+        let mut tables = vec![table];
+        for i in 1..8 {
+            let mut next = [0; 256];
+            for (byte, entry) in next.iter_mut().enumerate() {
+                let prev = tables[i - 1][byte];
+                *entry = if params.refin {
+                    (prev >> 8) ^ table[(prev & 0xFF) as usize]
+                } else {
+                    (prev << 8) ^ table[(prev >> 56) as usize]
+                };
+            }
+            tables.push(next);
+        }
+        Crc { params, tables }
+    }
+
+    /// CRC-32C (Castagnoli)
+    pub fn crc32c() -> Crc {
+        Crc::new(CRC_32_ISCSI)
+    }
+
+    /// CRC-64/XZ (the ECMA-182 polynomial)
+    pub fn crc64() -> Crc {
+        Crc::new(CRC_64_XZ)
+    }
+
+    pub fn params(&self) -> &CrcParams {
+        &self.params
+    }
+
+    /// The width of the checksum in bits
+    pub fn width(&self) -> u8 {
+        self.params.width
+    }
+
+    /// Calculates the checksum of the data
+    pub fn checksum(&self, data: &[u8]) -> u64 {
+        let mut digest = self.digest();
+        digest.update(data);
+        digest.finalize()
+    }
+
+    /// Returns an object calculating the checksum of data supplied in parts
+    pub fn digest(&self) -> Digest<'_> {
+        let register = if self.params.refin {
+            reflect(self.params.init, self.params.width)
+        } else {
+            self.params.init << (64 - self.params.width)
+        };
+        Digest {
+            crc: self,
+            register,
+        }
+    }
+}
+
+/// The state of a checksum calculation
+pub struct Digest<'a> {
+    crc: &'a Crc,
+    register: u64,
+}
+
+impl<'a> Digest<'a> {
+    /// Processes the next part of the data
+    pub fn update(&mut self, data: &[u8]) {
+        let t = &self.crc.tables;
+        let mut r = self.register;
+        let mut chunks = data.chunks(8);
+        let mut last = &[][..];
+        if self.crc.params.refin {
+            for chunk in &mut chunks {
+                if chunk.len() < 8 {
+                    last = chunk;
+                    break;
+                }
+                let mut x = r;
+                for (i, byte) in chunk.iter().enumerate() {
+                    x ^= (*byte as u64) << (8 * i);
+                }
+                r = t[7][(x & 0xFF) as usize] ^ t[6][((x >> 8) & 0xFF) as usize] ^
+                    t[5][((x >> 16) & 0xFF) as usize] ^
+                    t[4][((x >> 24) & 0xFF) as usize] ^
+                    t[3][((x >> 32) & 0xFF) as usize] ^
+                    t[2][((x >> 40) & 0xFF) as usize] ^
+                    t[1][((x >> 48) & 0xFF) as usize] ^ t[0][(x >> 56) as usize];
+            }
+            for byte in last {
+                r = t[0][((r ^ *byte as u64) & 0xFF) as usize] ^ (r >> 8);
+            }
+        } else {
+            for chunk in &mut chunks {
+                if chunk.len() < 8 {
+                    last = chunk;
+                    break;
+                }
+                let mut x = r;
+                for (i, byte) in chunk.iter().enumerate() {
+                    x ^= (*byte as u64) << (56 - 8 * i);
+                }
+                r = t[7][(x >> 56) as usize] ^ t[6][((x >> 48) & 0xFF) as usize] ^
+                    t[5][((x >> 40) & 0xFF) as usize] ^
+                    t[4][((x >> 32) & 0xFF) as usize] ^
+                    t[3][((x >> 24) & 0xFF) as usize] ^
+                    t[2][((x >> 16) & 0xFF) as usize] ^
+                    t[1][((x >> 8) & 0xFF) as usize] ^ t[0][(x & 0xFF) as usize];
+            }
+            for byte in last {
+                r = t[0][((r >> 56) ^ *byte as u64) as usize] ^ (r << 8);
+            }
+        }
+        self.register = r;
+    }
+
+    /// Returns the checksum of all the data processed so far
+    pub fn finalize(&self) -> u64 {
+        let params = &self.crc.params;
+        let width = params.width;
+        let register = if params.refin {
+            self.register
+        } else {
+            self.register >> (64 - width)
+        };
+        let result = if params.refin == params.refout {
+            register
+        } else {
+            reflect(register, width)
+        };
+        (result ^ params.xorout) & mask(width)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use super::{Crc, CATALOGUE};
+
+    /// Calculates the CRC one bit at a time, straight from the definition
+    fn bitwise(crc: &Crc, data: &[u8]) -> u64 {
+        let params = crc.params();
+        let top = 1 << (params.width - 1);
+        let mask = top | (top - 1);
+        let mut register = params.init;
+        for byte in data {
+            for i in 0..8 {
+                let bit = if params.refin {
+                    (byte >> i) & 1
+                } else {
+                    (byte >> (7 - i)) & 1
+                };
+                let feedback = (register & top != 0) != (bit == 1);
+                register = (register << 1) & mask;
+                if feedback {
+                    register ^= params.poly.0;
+                }
+            }
+        }
+        if params.refout {
+            register = super::reflect(register, params.width);
+        }
+        register ^ params.xorout
+    }
+
+    #[test]
+    fn test_check_values() {
+        for params in CATALOGUE {
+            let crc = Crc::new(*params);
+            assert_eq!(crc.checksum(b"123456789"), params.check, "{}", params.name);
+        }
+        assert_eq!(Crc::crc32c().checksum(b""), 0);
+    }
+
+    #[test]
+    fn test_slicing() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        for params in CATALOGUE {
+            let crc = Crc::new(*params);
+            for &len in &[0, 1, 7, 8, 9, 63, 1000] {
+                let expected = bitwise(&crc, &data[..len]);
+                assert_eq!(crc.checksum(&data[..len]), expected, "{}", params.name);
+                let mut digest = crc.digest();
+                digest.update(&data[..len / 3]);
+                digest.update(&data[len / 3..len]);
+                assert_eq!(digest.finalize(), expected, "{}", params.name);
+            }
+        }
+    }
+}
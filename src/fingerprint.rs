@@ -0,0 +1,91 @@
+use codec::{as_words, interpolate, DecodeError};
+use std::collections::HashSet;
+use gf232::GF232;
+use polygf232::PolyGF232;
+
+/// Calculates a fingerprint of a shard created by `encode`: the polynomial with the
+/// consecutive `u32`s of the shard as coefficients, evaluated at the secret `key`.
+/// Since the encoding is linear and every stripe is encoded in the same way, the
+/// fingerprints of the shards are themselves encoded like a single stripe - which
+/// lets a storage node be audited by comparing fingerprints instead of whole shards.
+pub fn fingerprint(shard: &[u8], key: GF232) -> GF232 {
+    PolyGF232::new(as_words(shard)).apply(key)
+}
+
+/// Checks whether the fingerprints of at least `k` shards, calculated with the same
+/// key, are consistent with each other - that is, whether the fingerprints of the
+/// parity shards match the combination of the fingerprints of the data shards.
+/// Every entry is a pair of a shard index and the fingerprint of the shard. Any `k`
+/// fingerprints are consistent, so only more than `k` of them can detect corruption.
+pub fn verify_fingerprints(
+    fingerprints: &[(usize, GF232)],
+    k: usize,
+) -> Result<bool, DecodeError> {
+    if fingerprints.len() < k {
+        return Err(DecodeError::NotEnoughShards {
+            found: fingerprints.len(),
+            required: k,
+        });
+    }
+    let mut indices = HashSet::new();
+    for &(idx, _) in fingerprints {
+        if !indices.insert(idx) {
+            return Err(DecodeError::DuplicateShardIndex(idx));
+        }
+    }
+    let interpolated = interpolate(fingerprints[..k].to_vec());
+    Ok(fingerprints[k..].iter().all(|&(idx, fingerprint)| {
+        interpolated.apply(GF232(idx as u32)) == fingerprint
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::{encode, DecodeError};
+    use gf232::GF232;
+    use super::{fingerprint, verify_fingerprints};
+
+    #[test]
+    fn test_fingerprints() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let (n, k) = (7, 4);
+        let mut encoded = encode(&data, n, k);
+        let key = GF232(rng.gen());
+
+        let fingerprints: Vec<_> = encoded
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| (idx, fingerprint(shard, key)))
+            .collect();
+        assert_eq!(verify_fingerprints(&fingerprints, k), Ok(true));
+        let mut shuffled = fingerprints.clone();
+        rng.shuffle(&mut shuffled);
+        assert_eq!(verify_fingerprints(&shuffled, k), Ok(true));
+
+        // corrupt a parity shard
+        encoded[5][17] ^= 0x40;
+        let mut corrupted = fingerprints.clone();
+        corrupted[5] = (5, fingerprint(&encoded[5], key));
+        assert_eq!(verify_fingerprints(&corrupted, k), Ok(false));
+        assert_eq!(verify_fingerprints(&corrupted[..5], k), Ok(true));
+
+        // corrupt a data shard
+        encoded[2][0] ^= 1;
+        let mut corrupted = fingerprints.clone();
+        corrupted[2] = (2, fingerprint(&encoded[2], key));
+        assert_eq!(verify_fingerprints(&corrupted, k), Ok(false));
+
+        let mut duplicated = fingerprints.clone();
+        duplicated[6] = fingerprints[1];
+        assert_eq!(
+            verify_fingerprints(&duplicated, k),
+            Err(DecodeError::DuplicateShardIndex(1))
+        );
+        assert_eq!(
+            verify_fingerprints(&fingerprints[..3], k),
+            Err(DecodeError::NotEnoughShards { found: 3, required: 4 })
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use polynomial::BinaryPolynomial;
+use std::ops::{Add, Sub, Mul, Div};
+use std::ops::{AddAssign, SubAssign, MulAssign, DivAssign};
+
+/// This is an irreducible degree-16 polynomial: X^16 + X^5 + X^3 + X^2 + 1
+const IRR216: BinaryPolynomial = BinaryPolynomial(0x1002D);
+
+/// A struct representing an element of the `GF(2^16)` Galois field.
+/// The elements of this field are binary polynomials mod an irreducible
+/// polynomial: IRR216
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GF216(pub u16);
+
+impl GF216 {
+    /// Finds an inverse of the element using Blankinship's algorithm
+    pub fn inverse(&self) -> GF216 {
+        if *self == GF216(0) {
+            panic!("Division by zero!");
+        }
+        let mut c = IRR216;
+        let mut d = BinaryPolynomial(self.0 as u64);
+        let mut n1 = BinaryPolynomial(0);
+        let mut n = BinaryPolynomial(1);
+        loop {
+            let q = c / d;
+            let r = c % d;
+            if r == BinaryPolynomial(0) {
+                break;
+            }
+            c = d;
+            d = r;
+            let t = n1;
+            n1 = n;
+            n = t - q * n;
+        }
+        GF216(n.0 as u16)
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl Add<GF216> for GF216 {
+    type Output = GF216;
+
+    fn add(self, other: GF216) -> GF216 {
+        GF216(self.0 ^ other.0)
+    }
+}
+
+impl AddAssign<GF216> for GF216 {
+    fn add_assign(&mut self, other: GF216) {
+        *self = *self + other;
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl Sub<GF216> for GF216 {
+    type Output = GF216;
+
+    fn sub(self, other: GF216) -> GF216 {
+        GF216(self.0 ^ other.0)
+    }
+}
+
+impl SubAssign<GF216> for GF216 {
+    fn sub_assign(&mut self, other: GF216) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<GF216> for GF216 {
+    type Output = GF216;
+
+    fn mul(self, other: GF216) -> GF216 {
+        let poly1 = BinaryPolynomial(self.0 as u64);
+        let poly2 = BinaryPolynomial(other.0 as u64);
+        let res = (poly1 * poly2) % IRR216;
+        GF216(res.0 as u16)
+    }
+}
+
+impl MulAssign<GF216> for GF216 {
+    fn mul_assign(&mut self, other: GF216) {
+        *self = *self * other;
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl Div<GF216> for GF216 {
+    type Output = GF216;
+
+    fn div(self, other: GF216) -> GF216 {
+        self * other.inverse()
+    }
+}
+
+impl DivAssign<GF216> for GF216 {
+    fn div_assign(&mut self, other: GF216) {
+        *self = *self / other;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GF216;
+
+    #[test]
+    fn test_inverse() {
+        for i in 1..=0xFFFF {
+            let x = GF216(i);
+            assert_eq!(x * x.inverse(), GF216(1), "x = {:?}", x);
+        }
+    }
+}
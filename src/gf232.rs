@@ -35,8 +35,67 @@ impl GF232 {
         }
         GF232(n.0 as u32)
     }
+
+    /// Replaces every element of the slice with its inverse using Montgomery's trick:
+    /// a single inversion of the product of all the elements, and `3 * (n - 1)`
+    /// multiplications
+    pub fn batch_inverse(values: &mut [GF232]) {
+        // prefix[i] is the product of the elements before the i-th one
+        let mut prefix = Vec::with_capacity(values.len());
+        let mut product = GF232(1);
+        for x in values.iter() {
+            if *x == GF232(0) {
+                panic!("Division by zero!");
+            }
+            prefix.push(product);
+            product *= *x;
+        }
+        // the inverse of the product of the elements up to and including the i-th one
+        let mut inverse = product.inverse();
+        for (x, before) in values.iter_mut().zip(prefix).rev() {
+            let value = *x;
+            *x = inverse * before;
+            inverse *= value;
+        }
+    }
+}
+
+/// The products of a fixed element of `GF(2^32)` with every possible byte at each of
+/// the 4 byte positions of the other factor, which reduce a multiplication by that
+/// element to 4 table lookups. Building the tables takes 1024 XORs.
+pub struct MulTable {
+    tables: [[u32; 256]; 4],
+}
+
+impl MulTable {
+    pub fn new(x: GF232) -> MulTable {
+        let mut tables = [[0; 256]; 4];
+        // x * X^i for the successive bits i of the other factor
+        let mut power = x.0;
+        for table in &mut tables {
+            for bit in 0..8 {
+                for byte in 1 << bit..2 << bit {
+                    table[byte] = table[byte ^ 1 << bit] ^ power;
+                }
+                let overflow = power >> 31;
+                power = (power << 1) ^ (overflow * IRR232.0 as u32);
+            }
+        }
+        MulTable { tables }
+    }
+
+    /// Multiplies the element the tables were built for by `y`
+    pub fn mul(&self, y: GF232) -> GF232 {
+        GF232(
+            self.tables[0][(y.0 & 0xFF) as usize] ^
+                self.tables[1][(y.0 >> 8 & 0xFF) as usize] ^
+                self.tables[2][(y.0 >> 16 & 0xFF) as usize] ^
+                self.tables[3][(y.0 >> 24) as usize],
+        )
+    }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl Add<GF232> for GF232 {
     type Output = GF232;
 
@@ -51,6 +110,7 @@ impl AddAssign<GF232> for GF232 {
     }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl Sub<GF232> for GF232 {
     type Output = GF232;
 
@@ -82,6 +142,7 @@ impl MulAssign<GF232> for GF232 {
     }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl Div<GF232> for GF232 {
     type Output = GF232;
 
@@ -98,7 +159,8 @@ impl DivAssign<GF232> for GF232 {
 
 #[cfg(test)]
 mod test {
-    use super::GF232;
+    use rand::{self, Rng};
+    use super::{GF232, MulTable};
 
     #[test]
     fn test_inverse() {
@@ -113,4 +175,24 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_batch_inverse() {
+        let mut values: Vec<GF232> = (1..1000u32).map(|i| GF232(i.wrapping_mul(0x9E37_79B9))).collect();
+        let expected: Vec<GF232> = values.iter().map(GF232::inverse).collect();
+        GF232::batch_inverse(&mut values);
+        assert_eq!(values, expected);
+        GF232::batch_inverse(&mut []);
+    }
+
+    #[test]
+    fn test_mul_table() {
+        let mut rng = rand::thread_rng();
+        for &x in &[0, 1, 2, 0x8000_0000, 0xFFFF_FFFF, rng.gen()] {
+            let table = MulTable::new(GF232(x));
+            for &y in &[0, 1, 0x80, 0x8000_0000, 0xFFFF_FFFF, rng.gen(), rng.gen()] {
+                assert_eq!(table.mul(GF232(y)), GF232(x) * GF232(y), "{:x} * {:x}", x, y);
+            }
+        }
+    }
 }
@@ -3,8 +3,48 @@ extern crate rand;
 
 mod polynomial;
 mod gf232;
+mod gf216;
 mod polygf232;
 mod codec;
+mod config;
+mod crc;
+mod sha256;
+mod merkle;
+mod fingerprint;
+mod por;
+mod lrc;
+mod matrix;
+mod msr;
+mod piggyback;
+mod cauchy;
+mod raid6;
+mod lt;
+mod rlnc;
+mod additive_fft;
 
 pub use codec::encode;
 pub use codec::decode;
+pub use codec::{encode_with_config, decode_with_config, verify_shard, DecodeError};
+pub use codec::{encode_with_commitment, decode_with_commitment};
+pub use codec::{update_parity, UpdateError};
+pub use codec::{encode_appendable, append, decode_appendable};
+pub use config::{CodecConfig, CodecConfigBuilder, ConfigError};
+pub use config::{MAX_SHARDS, DEFAULT_CHECKSUM_BLOCK_SIZE};
+pub use config::{Field, Layout, Framing, Checksum, Coding};
+pub use crc::{Crc, CrcParams, Digest, CATALOGUE};
+pub use polynomial::BinaryPolynomial;
+pub use gf232::GF232;
+pub use polygf232::PolyGF232;
+pub use gf216::GF216;
+pub use matrix::Matrix;
+pub use sha256::{sha256, Hash, Sha256};
+pub use merkle::{MerkleTree, leaf_hash, root_from_proof};
+pub use fingerprint::{fingerprint, verify_fingerprints};
+pub use por::{prove, Challenge, PorKey, Response};
+pub use lrc::{Lrc, RepairPlan};
+pub use msr::{simulate_repair, Msr, RepairStats};
+pub use piggyback::{substripe, PiggybackRs, Substripe};
+pub use raid6::{encode_raid6, decode_raid6, reconstruct_raid6};
+pub use lt::{LtDecoder, LtEncoder, LtSymbol};
+pub use rlnc::{Decoder as RlncDecoder, Encoder as RlncEncoder, Packet, Recoder};
+pub use additive_fft::{AdditiveFft, BinaryField, FftRs};
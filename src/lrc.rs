@@ -0,0 +1,377 @@
+use codec::{self, as_shard, as_words, encode_stripe, interpolate, DecodeError};
+use codec::{unframe, validate_shards};
+use config::{ConfigError, MAX_SHARDS};
+use gf232::GF232;
+use matrix::Matrix;
+use std::collections::HashMap;
+
+/// Local Reconstruction Code built on top of the Reed-Solomon encoder: the `k` data
+/// shards are split into groups, each of which gets a local parity shard (the sum of
+/// the shards in the group multiplied by their coefficients), in addition to the global
+/// parity shards created by `encode`.
+/// The shards are ordered as follows: `k` data shards, the global parity shards, the
+/// local parity shards. A lost data shard or local parity shard can be repaired by
+/// reading just the rest of its group.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lrc {
+    k: usize,
+    local_groups: usize,
+    global_parities: usize,
+}
+
+/// The way a lost shard gets repaired, with the indices of the shards which have to be
+/// read
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RepairPlan {
+    /// A combination of the other shards of the local group with the given index
+    /// (including its local parity)
+    Local(usize, Vec<usize>),
+    /// Reed-Solomon decoding from `k` data or global parity shards
+    Global(Vec<usize>),
+}
+
+impl RepairPlan {
+    /// The indices of the shards which have to be read
+    pub fn shards(&self) -> &[usize] {
+        match *self {
+            RepairPlan::Local(_, ref shards) | RepairPlan::Global(ref shards) => shards,
+        }
+    }
+}
+
+/// Returns the coefficient of the data shard `j` in its local parity. The global
+/// parities are evaluations at consecutive integers, which form additive subgroups of
+/// the field, so simple coefficients like all ones or powers of a generator make the
+/// local parities linearly dependent on them for many sets of shards. Pseudorandom ones
+/// (Fibonacci hashing of the index, which is never 0) avoid that with high probability.
+fn coefficient(j: usize) -> GF232 {
+    GF232((j as u32).wrapping_add(1).wrapping_mul(0x9E37_79B9))
+}
+
+impl Lrc {
+    /// Creates a code with `k` data shards split into `local_groups` groups of
+    /// (almost) equal sizes, with `global_parities` Reed-Solomon parity shards, of which
+    /// there has to be at least one
+    pub fn new(k: usize, local_groups: usize, global_parities: usize) -> Result<Lrc, ConfigError> {
+        if k == 0 {
+            return Err(ConfigError::NoDataShards);
+        }
+        if local_groups == 0 || local_groups > k {
+            return Err(ConfigError::Unsupported(
+                "the number of local groups has to be between 1 and the number of data shards",
+            ));
+        }
+        if global_parities == 0 {
+            return Err(ConfigError::Unsupported("there are no global parity shards"));
+        }
+        if (k + global_parities) as u64 > MAX_SHARDS {
+            return Err(ConfigError::TooManyShards(k + global_parities));
+        }
+        Ok(Lrc {
+            k,
+            local_groups,
+            global_parities,
+        })
+    }
+
+    /// The total number of shards
+    pub fn n(&self) -> usize {
+        self.k + self.global_parities + self.local_groups
+    }
+
+    /// The indices of the data shards belonging to the given group
+    fn group_members(&self, group: usize) -> Vec<usize> {
+        (0..self.k)
+            .filter(|&j| j * self.local_groups / self.k == group)
+            .collect()
+    }
+
+    /// Returns the local group containing the shard with the given index, or `None`
+    /// for the global parity shards
+    pub fn group_of(&self, idx: usize) -> Option<usize> {
+        if idx < self.k {
+            Some(idx * self.local_groups / self.k)
+        } else if idx >= self.k + self.global_parities && idx < self.n() {
+            Some(idx - self.k - self.global_parities)
+        } else {
+            None
+        }
+    }
+
+    /// The generator matrix of the code, with a row per shard mapping the `k` data words
+    /// of a stripe to the word of the shard
+    fn generator(&self) -> Matrix {
+        let n = self.k + self.global_parities;
+        let columns: Vec<Vec<u32>> = (0..self.k)
+            .map(|j| {
+                let mut unit = vec![0; self.k];
+                unit[j] = 1;
+                encode_stripe(&unit, n, self.k)
+            })
+            .collect();
+        let mut rows: Vec<Vec<GF232>> = (0..n)
+            .map(|i| columns.iter().map(|column| GF232(column[i])).collect())
+            .collect();
+        for group in 0..self.local_groups {
+            let mut row = vec![GF232(0); self.k];
+            for j in self.group_members(group) {
+                row[j] = coefficient(j);
+            }
+            rows.push(row);
+        }
+        Matrix::new(rows)
+    }
+
+    /// Encodes the data into `n()` shards
+    pub fn encode(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut result = codec::encode(data, self.k + self.global_parities, self.k);
+        for group in 0..self.local_groups {
+            let mut parity = vec![GF232(0); result[0].len() / 4];
+            for j in self.group_members(group) {
+                for (x, y) in parity.iter_mut().zip(as_words(&result[j])) {
+                    *x += coefficient(j) * y;
+                }
+            }
+            result.push(as_shard(&parity));
+        }
+        result
+    }
+
+    /// Returns the indices of the shards which have to be read in order to repair the
+    /// shard `lost`, given the indices of the available ones: the rest of its local
+    /// group if possible, any `k` data or global parity shards otherwise
+    pub fn repair_plan(&self, lost: usize, available: &[usize]) -> Option<RepairPlan> {
+        if let Some(group) = self.group_of(lost) {
+            let mut members = self.group_members(group);
+            members.push(self.k + self.global_parities + group);
+            members.retain(|&idx| idx != lost);
+            if members.iter().all(|idx| available.contains(idx)) {
+                return Some(RepairPlan::Local(group, members));
+            }
+        }
+        let global: Vec<usize> = available
+            .iter()
+            .cloned()
+            .filter(|&idx| idx != lost && idx < self.k + self.global_parities)
+            .take(self.k)
+            .collect();
+        if global.len() == self.k {
+            Some(RepairPlan::Global(global))
+        } else {
+            None
+        }
+    }
+
+    /// Repairs the shard `lost` from the available shards, reading only those returned
+    /// by `repair_plan`
+    pub fn repair(&self, lost: usize, shards: &[(usize, &[u8])]) -> Result<Vec<u8>, DecodeError> {
+        if lost >= self.n() {
+            return Err(DecodeError::InvalidShardIndex(lost));
+        }
+        validate_shards(shards, self.n(), 1, 4)?;
+        let available: Vec<usize> = shards.iter().map(|&(idx, _)| idx).collect();
+        let plan = match self.repair_plan(lost, &available) {
+            Some(plan) => plan,
+            None => {
+                return Err(DecodeError::NotEnoughShards {
+                    found: available.len(),
+                    required: self.k,
+                })
+            }
+        };
+        let shards: HashMap<usize, &[u8]> = shards.iter().cloned().collect();
+        let plan = match plan {
+            RepairPlan::Local(group, plan) => {
+                // the local parity plus the data shards of the group multiplied by their
+                // coefficients is 0
+                let parity = self.k + self.global_parities + group;
+                let weight = |idx: usize| if idx == parity { GF232(1) } else { coefficient(idx) };
+                let mut result = vec![GF232(0); shards[&plan[0]].len() / 4];
+                for &idx in &plan {
+                    for (x, y) in result.iter_mut().zip(as_words(shards[&idx])) {
+                        *x += weight(idx) * y;
+                    }
+                }
+                let factor = weight(lost).inverse();
+                for x in &mut result {
+                    *x *= factor;
+                }
+                return Ok(as_shard(&result));
+            }
+            RepairPlan::Global(plan) => plan,
+        };
+
+        // reconstruct the stripes from `k` shards
+        let words: Vec<Vec<GF232>> = plan.iter().map(|idx| as_words(shards[idx])).collect();
+        let targets = match self.group_of(lost) {
+            Some(_) if lost >= self.k => self.group_members(lost - self.k - self.global_parities),
+            _ => vec![lost],
+        };
+        let mut result = vec![];
+        for stripe in 0..words[0].len() {
+            let points = plan.iter()
+                .zip(&words)
+                .map(|(idx, words)| (*idx, words[stripe]))
+                .collect();
+            let interpolated = interpolate(points);
+            let mut value = GF232(0);
+            for &target in &targets {
+                let weight = if target == lost { GF232(1) } else { coefficient(target) };
+                value += weight * interpolated.apply(GF232(target as u32));
+            }
+            result.push(value);
+        }
+        Ok(as_shard(&result))
+    }
+
+    /// Decodes the data from any `k` shards whose rows of the generator matrix are
+    /// linearly independent, the local parities included. If there are no such `k`
+    /// shards, the returned error contains the number of linearly independent ones.
+    pub fn decode(&self, shards: &[(usize, &[u8])]) -> Result<Vec<u8>, DecodeError> {
+        validate_shards(shards, self.n(), self.k, 4)?;
+        let generator = self.generator();
+        let mut rows = vec![];
+        let mut words = vec![];
+        for &(idx, shard) in shards {
+            if rows.len() == self.k {
+                break;
+            }
+            rows.push(idx);
+            if generator.select_rows(&rows).rank() == rows.len() {
+                words.push(as_words(shard));
+            } else {
+                rows.pop();
+            }
+        }
+        if rows.len() < self.k {
+            return Err(DecodeError::NotEnoughShards {
+                found: rows.len(),
+                required: self.k,
+            });
+        }
+        let inverse = generator
+            .select_rows(&rows)
+            .inverse()
+            .expect("The rows are linearly independent");
+        let mut stream = vec![];
+        for stripe in 0..words[0].len() {
+            let symbols: Vec<GF232> = words.iter().map(|words| words[stripe]).collect();
+            stream.extend(inverse.mul_vec(&symbols));
+        }
+        unframe(&as_shard(&stream))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::DecodeError;
+    use config::ConfigError;
+    use super::{Lrc, RepairPlan};
+
+    #[test]
+    fn test_repair() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(500).collect();
+        // 6 data shards in 2 groups, 2 global parities, 2 local parities
+        let lrc = Lrc::new(6, 2, 2).unwrap();
+        let encoded = lrc.encode(&data);
+        assert_eq!(encoded.len(), 10);
+        let all: Vec<(usize, &[u8])> = encoded
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| (idx, &shard[..]))
+            .collect();
+
+        for (lost, shard) in encoded.iter().enumerate() {
+            let available: Vec<_> = all.iter().cloned().filter(|&(idx, _)| idx != lost).collect();
+            let indices: Vec<_> = available.iter().map(|&(idx, _)| idx).collect();
+            match (lrc.repair_plan(lost, &indices).unwrap(), lrc.group_of(lost)) {
+                // the rest of the group and the local parity
+                (RepairPlan::Local(group, plan), Some(lost_group)) => {
+                    assert_eq!(group, lost_group);
+                    assert_eq!(plan.len(), 3);
+                }
+                (RepairPlan::Global(plan), None) => assert_eq!(plan.len(), 6),
+                (plan, _) => panic!("Unexpected plan {:?} for shard {}", plan, lost),
+            }
+            assert_eq!(lrc.repair(lost, &available), Ok(shard.clone()));
+        }
+
+        // with two shards of a group lost, the global parities are needed
+        let available: Vec<_> = all.iter()
+            .cloned()
+            .filter(|&(idx, _)| idx != 0 && idx != 1)
+            .collect();
+        let indices: Vec<_> = available.iter().map(|&(idx, _)| idx).collect();
+        match lrc.repair_plan(0, &indices) {
+            Some(RepairPlan::Global(plan)) => assert_eq!(plan.len(), 6),
+            plan => panic!("Unexpected plan {:?}", plan),
+        }
+        assert_eq!(lrc.repair(0, &available), Ok(encoded[0].clone()));
+        assert_eq!(lrc.repair(8, &available), Ok(encoded[8].clone()));
+
+        assert_eq!(lrc.repair(10, &available), Err(DecodeError::InvalidShardIndex(10)));
+        let mut truncated = available.clone();
+        truncated[2].1 = &truncated[2].1[1..];
+        assert_eq!(lrc.repair(0, &truncated), Err(DecodeError::InvalidShardLength));
+        let mut duplicate = available.clone();
+        duplicate[2].0 = duplicate[3].0;
+        assert_eq!(
+            lrc.repair(0, &duplicate),
+            Err(DecodeError::DuplicateShardIndex(duplicate[3].0))
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(500).collect();
+        let lrc = Lrc::new(6, 2, 2).unwrap();
+        let encoded = lrc.encode(&data);
+
+        // data shards 1 and 4 lost, repaired locally; global parities lost too
+        let shards: Vec<(usize, &[u8])> = [0, 2, 3, 5, 8, 9]
+            .iter()
+            .map(|&idx| (idx, &encoded[idx][..]))
+            .collect();
+        assert_eq!(lrc.decode(&shards), Ok(data.clone()));
+
+        // three shards of the first group lost, solved for with the global parities and
+        // the local parity of the group
+        let shards: Vec<(usize, &[u8])> = [3, 4, 5, 6, 7, 8]
+            .iter()
+            .map(|&idx| (idx, &encoded[idx][..]))
+            .collect();
+        assert_eq!(lrc.decode(&shards), Ok(data.clone()));
+
+        // the local parity of the second group doesn't help with the first one
+        let shards: Vec<(usize, &[u8])> = [3, 4, 5, 6, 7, 9]
+            .iter()
+            .map(|&idx| (idx, &encoded[idx][..]))
+            .collect();
+        assert_eq!(
+            lrc.decode(&shards),
+            Err(DecodeError::NotEnoughShards { found: 5, required: 6 })
+        );
+
+        let mut shards: Vec<(usize, &[u8])> = encoded
+            .iter()
+            .enumerate()
+            .map(|(idx, shard)| (idx, &shard[..]))
+            .collect();
+        shards[4].1 = &shards[4].1[..8];
+        assert_eq!(lrc.decode(&shards), Err(DecodeError::InvalidShardLength));
+        shards[4] = shards[5];
+        assert_eq!(lrc.decode(&shards), Err(DecodeError::DuplicateShardIndex(5)));
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(Lrc::new(0, 0, 2), Err(ConfigError::NoDataShards));
+        assert!(Lrc::new(6, 0, 2).is_err());
+        assert!(Lrc::new(6, 7, 2).is_err());
+        assert!(Lrc::new(6, 2, 0).is_err());
+        assert_eq!(Lrc::new(6, 6, 1).map(|lrc| lrc.n()), Ok(13));
+    }
+}
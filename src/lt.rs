@@ -0,0 +1,295 @@
+use codec::{data_shards, join_data_shards, DecodeError};
+use std::mem;
+
+/// The parameters of the robust soliton distribution: `c` scales the expected number of
+/// source symbols of degree 1 in the ripple, and `delta` bounds the probability of the
+/// decoding failing after `k + O(sqrt(k) * ln(k / delta)^2)` symbols
+const SOLITON_C: f64 = 0.1;
+const SOLITON_DELTA: f64 = 0.05;
+
+/// A small pseudorandom generator (SplitMix64). The LT code uses it to derive the degree
+/// and the neighbours of an encoded symbol from its id, so that both sides agree on them.
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed number in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Returns the cumulative robust soliton distribution over the degrees `1..k + 1`
+fn robust_soliton(k: usize) -> Vec<f64> {
+    let kf = k as f64;
+    let r = SOLITON_C * (kf / SOLITON_DELTA).ln() * kf.sqrt();
+    let spike = ((kf / r).round() as usize).clamp(1, k);
+    let mut weights = vec![];
+    for d in 1..k + 1 {
+        let rho = if d == 1 { 1.0 / kf } else { 1.0 / (d * (d - 1)) as f64 };
+        let tau = if d < spike {
+            r / (d as f64 * kf)
+        } else if d == spike {
+            r * (r / SOLITON_DELTA).ln().max(0.0) / kf
+        } else {
+            0.0
+        };
+        weights.push(rho + tau);
+    }
+    let total: f64 = weights.iter().sum();
+    let mut cumulative = 0.0;
+    weights
+        .into_iter()
+        .map(|w| {
+            cumulative += w / total;
+            cumulative
+        })
+        .collect()
+}
+
+/// Returns the indices of the source symbols combined in the encoded symbol with the
+/// given id
+fn neighbours(id: u32, k: usize, distribution: &[f64]) -> Vec<usize> {
+    let mut rng = SplitMix64(id as u64 ^ (k as u64) << 32);
+    let sample = rng.next_f64();
+    let degree = distribution
+        .iter()
+        .position(|&c| sample < c)
+        .unwrap_or(k - 1) + 1;
+    let mut result = vec![];
+    while result.len() < degree {
+        let idx = rng.below(k);
+        if !result.contains(&idx) {
+            result.push(idx);
+        }
+    }
+    result.sort();
+    result
+}
+
+fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (x, y) in target.iter_mut().zip(source) {
+        *x ^= *y;
+    }
+}
+
+/// An encoded symbol: the XOR of the source symbols determined by the id
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LtSymbol {
+    pub id: u32,
+    pub data: Vec<u8>,
+}
+
+/// Rateless encoder generating any number of symbols, any slightly more than `k` of
+/// which are sufficient to decode the data with high probability. The `k` source
+/// symbols have the same contents as the data shards created by `encode`.
+pub struct LtEncoder {
+    source: Vec<Vec<u8>>,
+    distribution: Vec<f64>,
+}
+
+impl LtEncoder {
+    /// Creates an encoder for the data, split into `k > 0` source symbols
+    pub fn new(data: &[u8], k: usize) -> LtEncoder {
+        assert!(k > 0, "No source symbols");
+        LtEncoder {
+            source: data_shards(data, k),
+            distribution: robust_soliton(k),
+        }
+    }
+
+    /// Returns the encoded symbol with the given id
+    pub fn symbol(&self, id: u32) -> LtSymbol {
+        let mut data = vec![0; self.source[0].len()];
+        for idx in neighbours(id, self.source.len(), &self.distribution) {
+            xor_into(&mut data, &self.source[idx]);
+        }
+        LtSymbol { id, data }
+    }
+}
+
+/// Decoder collecting encoded symbols. The source symbols are recovered by peeling
+/// (a symbol with a single unknown neighbour reveals it) as the symbols arrive; if
+/// peeling stalls, `decode` falls back to Gaussian elimination over GF(2).
+pub struct LtDecoder {
+    k: usize,
+    distribution: Vec<f64>,
+    received: usize,
+    /// The length of the symbols, set by the first one received
+    length: Option<usize>,
+    source: Vec<Option<Vec<u8>>>,
+    /// The symbols with more than one unknown neighbour, reduced by the known ones
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+}
+
+impl LtDecoder {
+    /// Creates a decoder of `k > 0` source symbols
+    pub fn new(k: usize) -> LtDecoder {
+        assert!(k > 0, "No source symbols");
+        LtDecoder {
+            k,
+            distribution: robust_soliton(k),
+            received: 0,
+            length: None,
+            source: vec![None; k],
+            pending: vec![],
+        }
+    }
+
+    /// The number of source symbols recovered so far
+    pub fn recovered(&self) -> usize {
+        self.source.iter().filter(|s| s.is_some()).count()
+    }
+
+    /// Whether all the source symbols have been recovered by peeling
+    pub fn is_complete(&self) -> bool {
+        self.recovered() == self.k
+    }
+
+    /// Adds a received symbol, recovering all the source symbols which can be peeled.
+    /// Returns an error, ignoring the symbol, if its length is different from the
+    /// length of the first one, or isn't a nonzero multiple of 4.
+    pub fn add_symbol(&mut self, symbol: &LtSymbol) -> Result<(), DecodeError> {
+        let length = symbol.data.len();
+        if length == 0 || !length.is_multiple_of(4) || self.length.is_some_and(|l| l != length) {
+            return Err(DecodeError::InvalidShardLength);
+        }
+        self.length = Some(length);
+        self.received += 1;
+        let neighbours = neighbours(symbol.id, self.k, &self.distribution);
+        self.pending.push((neighbours, symbol.data.clone()));
+        let mut progress = true;
+        while progress {
+            progress = false;
+            let mut still_pending = vec![];
+            for (mut neighbours, mut data) in mem::take(&mut self.pending) {
+                let source = &self.source;
+                neighbours.retain(|&idx| match source[idx] {
+                    Some(ref known) => {
+                        xor_into(&mut data, known);
+                        false
+                    }
+                    None => true,
+                });
+                match neighbours.len() {
+                    0 => (),
+                    1 => {
+                        self.source[neighbours[0]] = Some(data);
+                        progress = true;
+                    }
+                    _ => still_pending.push((neighbours, data)),
+                }
+            }
+            self.pending = still_pending;
+        }
+        Ok(())
+    }
+
+    /// Solves for the source symbols which couldn't be peeled using Gaussian
+    /// elimination over GF(2)
+    fn eliminate(&self) -> Option<Vec<Vec<u8>>> {
+        let unknown: Vec<usize> = (0..self.k).filter(|&idx| self.source[idx].is_none()).collect();
+        let words = unknown.len().div_ceil(64);
+        let mut rows: Vec<(Vec<u64>, Vec<u8>)> = self.pending
+            .iter()
+            .map(|(neighbours, data)| {
+                let mut bits = vec![0u64; words];
+                for idx in neighbours {
+                    let col = unknown.binary_search(idx).unwrap();
+                    bits[col / 64] |= 1 << (col % 64);
+                }
+                (bits, data.clone())
+            })
+            .collect();
+        for col in 0..unknown.len() {
+            let (word, bit) = (col / 64, 1u64 << (col % 64));
+            let pivot = (col..rows.len()).find(|&r| rows[r].0[word] & bit != 0)?;
+            rows.swap(col, pivot);
+            let (pivot_bits, pivot_data) = rows[col].clone();
+            for (r, row) in rows.iter_mut().enumerate() {
+                if r != col && row.0[word] & bit != 0 {
+                    for (x, y) in row.0.iter_mut().zip(&pivot_bits) {
+                        *x ^= *y;
+                    }
+                    xor_into(&mut row.1, &pivot_data);
+                }
+            }
+        }
+        let mut source = self.source.clone();
+        for (col, &idx) in unknown.iter().enumerate() {
+            source[idx] = Some(rows[col].1.clone());
+        }
+        Some(source.into_iter().map(Option::unwrap).collect())
+    }
+
+    /// Decodes the data from the symbols received so far
+    pub fn decode(&self) -> Result<Vec<u8>, DecodeError> {
+        let error = DecodeError::NotEnoughShards {
+            found: self.received,
+            required: self.k,
+        };
+        let source: Vec<Vec<u8>> = if self.is_complete() {
+            self.source.iter().cloned().map(Option::unwrap).collect()
+        } else {
+            self.eliminate().ok_or(error)?
+        };
+        join_data_shards(&source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::DecodeError;
+    use super::{LtDecoder, LtEncoder};
+
+    #[test]
+    fn test_decode() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(5000).collect();
+        for &k in &[10, 50, 200] {
+            let encoder = LtEncoder::new(&data, k);
+            let mut decoder = LtDecoder::new(k);
+            let mut eliminated = false;
+            // a lossy channel: every other symbol is lost
+            for id in (0..).step_by(2) {
+                assert_eq!(decoder.add_symbol(&encoder.symbol(id)), Ok(()));
+                if let Ok(decoded) = decoder.decode() {
+                    assert_eq!(decoded, data);
+                    eliminated = !decoder.is_complete();
+                    break;
+                }
+                assert!(id < 8 * k as u32);
+            }
+            // peeling completes once enough symbols arrive
+            let mut id = 1;
+            while !decoder.is_complete() {
+                assert_eq!(decoder.add_symbol(&encoder.symbol(id)), Ok(()));
+                id += 2;
+                assert!(id < 16 * k as u32);
+            }
+            assert_eq!(decoder.decode(), Ok(data.clone()));
+            let mut symbol = encoder.symbol(id);
+            symbol.data.pop();
+            assert_eq!(decoder.add_symbol(&symbol), Err(DecodeError::InvalidShardLength));
+            symbol.data.truncate(symbol.data.len() - 3);
+            assert_eq!(decoder.add_symbol(&symbol), Err(DecodeError::InvalidShardLength));
+            // Gaussian elimination succeeds before peeling does
+            if k > 10 {
+                assert!(eliminated);
+            }
+        }
+    }
+}
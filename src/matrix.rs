@@ -0,0 +1,290 @@
+use gf232::GF232;
+use std::ops::{Index, IndexMut, Mul};
+
+/// A matrix over the `GF(2^32)` field, stored as a list of rows
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Matrix {
+    cols: usize,
+    rows: Vec<Vec<GF232>>,
+}
+
+impl Matrix {
+    /// Creates a matrix from its rows, which all have to be of the same length
+    pub fn new(rows: Vec<Vec<GF232>>) -> Matrix {
+        let cols = rows.first().map_or(0, Vec::len);
+        assert!(rows.iter().all(|row| row.len() == cols), "Rows of different lengths");
+        Matrix { cols, rows }
+    }
+
+    pub fn zero(rows: usize, cols: usize) -> Matrix {
+        Matrix {
+            cols,
+            rows: vec![vec![GF232(0); cols]; rows],
+        }
+    }
+
+    pub fn identity(size: usize) -> Matrix {
+        let mut result = Matrix::zero(size, size);
+        for i in 0..size {
+            result[i][i] = GF232(1);
+        }
+        result
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Returns the matrix consisting of the given rows of this one
+    pub fn select_rows(&self, indices: &[usize]) -> Matrix {
+        Matrix {
+            cols: self.cols,
+            rows: indices.iter().map(|&i| self.rows[i].clone()).collect(),
+        }
+    }
+
+    /// Multiplies the matrix by a column vector
+    pub fn mul_vec(&self, v: &[GF232]) -> Vec<GF232> {
+        assert_eq!(v.len(), self.cols);
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut result = GF232(0);
+                for (a, b) in row.iter().zip(v) {
+                    result += *a * *b;
+                }
+                result
+            })
+            .collect()
+    }
+
+    /// Brings the matrix to reduced row echelon form using Gauss-Jordan elimination.
+    /// Returns the columns of the pivots, whose number is the rank of the matrix.
+    pub fn row_reduce(&mut self) -> Vec<usize> {
+        let mut pivots = vec![];
+        for col in 0..self.cols {
+            let top = pivots.len();
+            let pivot = match (top..self.rows()).find(|&row| self.rows[row][col] != GF232(0)) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            self.rows.swap(top, pivot);
+            let factor = self.rows[top][col].inverse();
+            for x in &mut self.rows[top] {
+                *x *= factor;
+            }
+            let pivot_row = self.rows[top].clone();
+            for (row, values) in self.rows.iter_mut().enumerate() {
+                let factor = values[col];
+                if row == top || factor == GF232(0) {
+                    continue;
+                }
+                for (x, y) in values.iter_mut().zip(&pivot_row) {
+                    *x -= factor * *y;
+                }
+            }
+            pivots.push(col);
+            if pivots.len() == self.rows() {
+                break;
+            }
+        }
+        pivots
+    }
+
+    pub fn rank(&self) -> usize {
+        self.clone().row_reduce().len()
+    }
+
+    /// Calculates the determinant of a square matrix as the product of the pivots found
+    /// during elimination (swapping rows doesn't change the sign in characteristic 2)
+    pub fn determinant(&self) -> GF232 {
+        assert_eq!(self.rows(), self.cols, "Not a square matrix");
+        let mut rows = self.rows.clone();
+        let mut result = GF232(1);
+        for col in 0..self.cols {
+            let pivot = match (col..rows.len()).find(|&row| rows[row][col] != GF232(0)) {
+                Some(pivot) => pivot,
+                None => return GF232(0),
+            };
+            rows.swap(col, pivot);
+            result *= rows[col][col];
+            let factor = rows[col][col].inverse();
+            let pivot_row = rows[col].clone();
+            for row in &mut rows[col + 1..] {
+                let factor = row[col] * factor;
+                if factor == GF232(0) {
+                    continue;
+                }
+                for (x, y) in row.iter_mut().zip(&pivot_row) {
+                    *x -= factor * *y;
+                }
+            }
+        }
+        result
+    }
+
+    /// Finds the inverse of a square matrix. Returns `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix> {
+        assert_eq!(self.rows(), self.cols, "Not a square matrix");
+        let size = self.cols;
+        let mut augmented = Matrix::new(
+            self.rows
+                .iter()
+                .zip(Matrix::identity(size).rows)
+                .map(|(row, unit)| row.iter().cloned().chain(unit).collect())
+                .collect(),
+        );
+        if augmented.row_reduce().iter().any(|&col| col >= size) {
+            return None;
+        }
+        Some(Matrix {
+            cols: size,
+            rows: augmented.rows.into_iter().map(|row| row[size..].to_vec()).collect(),
+        })
+    }
+
+    /// Solves `self * x = b`. Returns `None` if there is no solution or if it isn't unique.
+    pub fn solve(&self, b: &[GF232]) -> Option<Vec<GF232>> {
+        assert_eq!(b.len(), self.rows());
+        let mut augmented = Matrix::new(
+            self.rows
+                .iter()
+                .zip(b)
+                .map(|(row, value)| {
+                    let mut row = row.clone();
+                    row.push(*value);
+                    row
+                })
+                .collect(),
+        );
+        let pivots = augmented.row_reduce();
+        if pivots.len() != self.cols || pivots.contains(&self.cols) {
+            return None;
+        }
+        Some(augmented.rows[..self.cols].iter().map(|row| row[self.cols]).collect())
+    }
+
+    /// Converts a generator matrix, with a row per shard mapping the `k` data symbols to
+    /// the contents of the shard, to systematic form, in which the first `k` shards are
+    /// the data symbols themselves: `G * G_k^-1`, where `G_k` are the first `k` rows.
+    /// Returns `None` if `G_k` is singular.
+    pub fn systematic(&self) -> Option<Matrix> {
+        let top: Vec<usize> = (0..self.cols).collect();
+        let inverse = self.select_rows(&top).inverse()?;
+        Some(self * &inverse)
+    }
+}
+
+impl Index<usize> for Matrix {
+    type Output = [GF232];
+
+    fn index(&self, row: usize) -> &[GF232] {
+        &self.rows[row]
+    }
+}
+
+impl IndexMut<usize> for Matrix {
+    fn index_mut(&mut self, row: usize) -> &mut [GF232] {
+        &mut self.rows[row]
+    }
+}
+
+impl<'a> Mul<&'a Matrix> for &'a Matrix {
+    type Output = Matrix;
+
+    fn mul(self, other: &Matrix) -> Matrix {
+        assert_eq!(self.cols, other.rows(), "Incompatible dimensions");
+        let mut result = Matrix::zero(self.rows(), other.cols);
+        for (i, row) in self.rows.iter().enumerate() {
+            for (a, other_row) in row.iter().zip(&other.rows) {
+                if *a == GF232(0) {
+                    continue;
+                }
+                for (x, b) in result.rows[i].iter_mut().zip(other_row) {
+                    *x += *a * *b;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use gf232::GF232;
+    use super::Matrix;
+
+    fn random(rows: usize, cols: usize) -> Matrix {
+        let mut rng = rand::thread_rng();
+        Matrix::new(
+            (0..rows)
+                .map(|_| (0..cols).map(|_| GF232(rng.gen())).collect())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_inverse() {
+        for size in 1..12 {
+            let matrix = random(size, size);
+            let inverse = matrix.inverse().unwrap();
+            assert_eq!(&matrix * &inverse, Matrix::identity(size));
+            assert_eq!(&inverse * &matrix, Matrix::identity(size));
+            assert_eq!(matrix.rank(), size);
+            assert_eq!(matrix.determinant() * inverse.determinant(), GF232(1));
+
+            let other = random(size, size);
+            assert_eq!(
+                (&matrix * &other).determinant(),
+                matrix.determinant() * other.determinant()
+            );
+
+            let b: Vec<GF232> = random(1, size)[0].to_vec();
+            let x = matrix.solve(&b).unwrap();
+            assert_eq!(matrix.mul_vec(&x), b);
+            assert_eq!(inverse.mul_vec(&b), x);
+        }
+    }
+
+    #[test]
+    fn test_singular() {
+        let mut matrix = random(6, 6);
+        let (first, second) = (matrix[1].to_vec(), matrix[4].to_vec());
+        for (x, (a, b)) in matrix[3].iter_mut().zip(first.iter().zip(&second)) {
+            *x = *a * GF232(7) + *b;
+        }
+        assert_eq!(matrix.rank(), 5);
+        assert_eq!(matrix.determinant(), GF232(0));
+        assert_eq!(matrix.inverse(), None);
+        let b = matrix.mul_vec(&random(1, 6)[0]);
+        assert_eq!(matrix.solve(&b), None);
+
+        // an overdetermined system which is consistent has a unique solution
+        let tall = random(8, 5);
+        let x = random(1, 5)[0].to_vec();
+        let b = tall.mul_vec(&x);
+        assert_eq!(tall.solve(&b), Some(x));
+        let mut b = b;
+        b[7] += GF232(1);
+        assert_eq!(tall.solve(&b), None);
+    }
+
+    #[test]
+    fn test_systematic() {
+        let generator = random(9, 4);
+        let systematic = generator.systematic().unwrap();
+        assert_eq!(systematic.select_rows(&[0, 1, 2, 3]), Matrix::identity(4));
+        // the code is the same: any 4 shards of one encoding map to the same data
+        let data = random(1, 4)[0].to_vec();
+        let encoded = generator.mul_vec(&data);
+        let rows = [2, 5, 7, 8];
+        let coded: Vec<GF232> = rows.iter().map(|&r| encoded[r]).collect();
+        let symbols = systematic.select_rows(&rows).solve(&coded).unwrap();
+        assert_eq!(systematic.mul_vec(&symbols), encoded);
+    }
+}
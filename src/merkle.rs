@@ -0,0 +1,138 @@
+use sha256::{Hash, Sha256};
+
+/// Returns the hash of a leaf (the hashes of leaves and inner nodes are prefixed with
+/// different bytes, so that one can't be passed off as the other)
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(&[0]);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(&[1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize()
+}
+
+/// A Merkle tree over a list of leaf hashes, built like in RFC 6962: a tree over
+/// `n > 1` leaves consists of a complete tree over the first `k` leaves, where `k` is
+/// the largest power of 2 less than `n` (so that `k < n <= 2k`), and a tree over the
+/// remaining ones.
+/// The same tree is built bottom-up by hashing pairs of consecutive nodes of every
+/// level, with the last node of a level of odd length promoted unchanged to the next
+/// one, so all the levels are kept and a proof is read off them without hashing.
+pub struct MerkleTree {
+    /// The levels of the tree, starting with the leaves and ending with the root
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Hash>) -> MerkleTree {
+        assert!(!leaves.is_empty());
+        let mut levels = vec![leaves];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { levels }
+    }
+
+    pub fn root(&self) -> Hash {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns the hashes of the siblings of the nodes on the path from the leaf with
+    /// the given index to the root, starting at the bottom
+    pub fn proof(&self, index: usize) -> Vec<Hash> {
+        assert!(index < self.levels[0].len());
+        let mut result = vec![];
+        let mut index = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                result.push(*sibling);
+            }
+            index >>= 1;
+        }
+        result
+    }
+}
+
+/// Calculates the root of a tree over `size` leaves from the hash of the leaf with the
+/// given index and its proof. Returns `None` if the proof is of a wrong length.
+pub fn root_from_proof(index: usize, size: usize, leaf: Hash, proof: &[Hash]) -> Option<Hash> {
+    if index >= size {
+        return None;
+    }
+    let (mut f, mut s) = (index, size - 1);
+    let mut result = leaf;
+    for hash in proof {
+        if s == 0 {
+            return None;
+        }
+        if f & 1 == 1 || f == s {
+            result = node_hash(hash, &result);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            result = node_hash(&result, hash);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    if s == 0 { Some(result) } else { None }
+}
+
+#[cfg(test)]
+mod test {
+    use sha256::Hash;
+    use super::{leaf_hash, node_hash, root_from_proof, MerkleTree};
+
+    /// Returns the largest power of 2 less than `n`, which has to be greater than 1
+    fn split_point(n: usize) -> usize {
+        let mut k = 1;
+        while k * 2 < n {
+            k *= 2;
+        }
+        k
+    }
+
+    /// The root of the tree as defined recursively by RFC 6962
+    fn subtree_root(leaves: &[Hash]) -> Hash {
+        if leaves.len() == 1 {
+            return leaves[0];
+        }
+        let k = split_point(leaves.len());
+        node_hash(&subtree_root(&leaves[..k]), &subtree_root(&leaves[k..]))
+    }
+
+    #[test]
+    fn test_proofs() {
+        for size in 1..20 {
+            let leaves: Vec<_> = (0..size).map(|i| leaf_hash(&[i as u8])).collect();
+            let tree = MerkleTree::new(leaves.clone());
+            let root = tree.root();
+            assert_eq!(root, subtree_root(&leaves));
+            for i in 0..size {
+                let proof = tree.proof(i);
+                assert_eq!(root_from_proof(i, size, leaves[i], &proof), Some(root));
+                if size > 1 {
+                    let other = (i + 1) % size;
+                    assert!(root_from_proof(other, size, leaves[i], &proof) != Some(root));
+                    assert!(root_from_proof(i, size, leaves[other], &proof) != Some(root));
+                    assert_eq!(root_from_proof(i, size, leaves[i], &proof[1..]), None);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use codec::{as_shard, as_words, frame, unframe, validate_shards, DecodeError};
+use config::{ConfigError, MAX_SHARDS};
+use gf232::GF232;
+use matrix::Matrix;
+use std::collections::HashSet;
+
+/// Minimum-storage regenerating code using the product-matrix construction of Rashmi,
+/// Shah and Kumar, with `d = 2k - 2` helpers and `alpha = k - 1` sub-packets per node.
+///
+/// Every stripe of `k * alpha` `u32`s is arranged into a message matrix `M = [S1; S2]`,
+/// where `S1` and `S2` are symmetric `alpha x alpha` matrices, and node `i` stores
+/// `psi_i^T M`, where `psi_i = (1, x_i, ..., x_i^(d - 1))`. Any `k` nodes can recover
+/// the data, and a failed node can be regenerated by downloading a single `u32` per
+/// stripe from each of `d` helpers - that is, `d / alpha = 2` shards' worth of data
+/// instead of the `k` shards needed to decode and re-encode.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Msr {
+    n: usize,
+    k: usize,
+    /// The evaluation points `x_i` of the nodes, such that all the `x_i^alpha` are
+    /// distinct
+    points: Vec<GF232>,
+    /// The rows of the matrix mapping a stripe of the message to the contents of the
+    /// nodes, `alpha` rows per node
+    generator: Matrix,
+}
+
+/// The numbers of bytes downloaded in order to replace a failed node
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RepairStats {
+    /// Bytes downloaded from the helpers when regenerating the node
+    pub regenerating: usize,
+    /// Bytes downloaded when decoding the data and encoding the node again
+    pub naive: usize,
+}
+
+fn pow(x: GF232, e: usize) -> GF232 {
+    let mut result = GF232(1);
+    for _ in 0..e {
+        result *= x;
+    }
+    result
+}
+
+impl Msr {
+    /// Creates a code with `n` nodes, any `k` of which can recover the data. `k` has to
+    /// be at least 2, and `n` at least `d + 1 = 2k - 1`.
+    pub fn new(n: usize, k: usize) -> Result<Msr, ConfigError> {
+        if k == 0 {
+            return Err(ConfigError::NoDataShards);
+        }
+        if k == 1 {
+            return Err(ConfigError::Unsupported("an MSR code needs at least 2 data shards"));
+        }
+        if n < 2 * k - 1 {
+            return Err(ConfigError::Unsupported("an MSR code needs at least 2k - 1 shards"));
+        }
+        if n as u64 > MAX_SHARDS {
+            return Err(ConfigError::TooManyShards(n));
+        }
+        let alpha = k - 1;
+        let mut points = vec![];
+        let mut lambdas = HashSet::new();
+        let mut x = 1u64;
+        while points.len() < n {
+            if x == MAX_SHARDS {
+                return Err(ConfigError::Unsupported(
+                    "there are not enough field elements with distinct powers for the nodes",
+                ));
+            }
+            if lambdas.insert(pow(GF232(x as u32), alpha).0) {
+                points.push(GF232(x as u32));
+            }
+            x += 1;
+        }
+
+        // the symbols of the message are the upper triangles of S1 and S2
+        let half = alpha * (alpha + 1) / 2;
+        let symbol = |a: usize, b: usize| {
+            let (a, b) = if a <= b { (a, b) } else { (b, a) };
+            a * alpha - a * (a.saturating_sub(1)) / 2 + (b - a)
+        };
+        let mut generator = vec![];
+        for x in &points {
+            for l in 0..alpha {
+                let mut row = vec![GF232(0); k * alpha];
+                for a in 0..alpha {
+                    row[symbol(a, l)] += pow(*x, a);
+                    row[half + symbol(a, l)] += pow(*x, alpha + a);
+                }
+                generator.push(row);
+            }
+        }
+        Ok(Msr {
+            n,
+            k,
+            points,
+            generator: Matrix::new(generator),
+        })
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The number of helpers needed to regenerate a node
+    pub fn d(&self) -> usize {
+        2 * self.k - 2
+    }
+
+    /// The number of `u32`s stored by every node per stripe
+    pub fn alpha(&self) -> usize {
+        self.k - 1
+    }
+
+    /// Encodes the data into `n` shards. The length of the data is prepended to it, and
+    /// it is padded to a multiple of `k * alpha` `u32`s.
+    pub fn encode(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let stream = frame(data, self.k * self.alpha() * 4);
+        let mut result = vec![vec![]; self.n];
+        for stripe in as_words(&stream).chunks(self.k * self.alpha()) {
+            let encoded = self.generator.mul_vec(stripe);
+            for (shard, symbols) in result.iter_mut().zip(encoded.chunks(self.alpha())) {
+                shard.extend(as_shard(symbols));
+            }
+        }
+        result
+    }
+
+    /// Decodes the data from any `k` shards
+    pub fn decode(&self, shards: &[(usize, &[u8])]) -> Result<Vec<u8>, DecodeError> {
+        let alpha = self.alpha();
+        validate_shards(shards, self.n, self.k, alpha * 4)?;
+        let shards = &shards[..self.k];
+        let rows: Vec<usize> = shards
+            .iter()
+            .flat_map(|&(idx, _)| idx * alpha..(idx + 1) * alpha)
+            .collect();
+        let inverse = self.generator
+            .select_rows(&rows)
+            .inverse()
+            .expect("Any k nodes should be able to recover the data");
+        let words: Vec<Vec<GF232>> = shards.iter().map(|&(_, shard)| as_words(shard)).collect();
+
+        let mut stream = vec![];
+        for stripe in 0..words[0].len() / alpha {
+            let symbols: Vec<GF232> = words
+                .iter()
+                .flat_map(|w| w[stripe * alpha..(stripe + 1) * alpha].to_vec())
+                .collect();
+            stream.extend(inverse.mul_vec(&symbols));
+        }
+        unframe(&as_shard(&stream))
+    }
+
+    /// Calculates the data which a helper node sends in order to regenerate the failed
+    /// node: the contents of its shard multiplied by `phi_f`, a single `u32` per stripe
+    pub fn helper_data(&self, shard: &[u8], failed: usize) -> Vec<u8> {
+        let alpha = self.alpha();
+        let phi = Matrix::new(vec![(0..alpha).map(|l| pow(self.points[failed], l)).collect()]);
+        let words = as_words(shard);
+        let result: Vec<GF232> = words
+            .chunks(alpha)
+            .map(|symbols| phi.mul_vec(symbols)[0])
+            .collect();
+        as_shard(&result)
+    }
+
+    /// Regenerates the shard of the failed node from the data sent by `d` helpers
+    pub fn regenerate(
+        &self,
+        failed: usize,
+        helpers: &[(usize, &[u8])],
+    ) -> Result<Vec<u8>, DecodeError> {
+        if failed >= self.n {
+            return Err(DecodeError::InvalidShardIndex(failed));
+        }
+        validate_shards(helpers, self.n, self.d(), 4)?;
+        let alpha = self.alpha();
+        let helpers = &helpers[..self.d()];
+        if let Some(&(idx, _)) = helpers.iter().find(|&&(idx, _)| idx == failed) {
+            return Err(DecodeError::DuplicateShardIndex(idx));
+        }
+        let rows = helpers
+            .iter()
+            .map(|&(idx, _)| (0..self.d()).map(|a| pow(self.points[idx], a)).collect())
+            .collect();
+        let inverse = Matrix::new(rows)
+            .inverse()
+            .expect("Any d nodes should be able to regenerate a node");
+        let words: Vec<Vec<GF232>> = helpers.iter().map(|&(_, data)| as_words(data)).collect();
+        let lambda = pow(self.points[failed], alpha);
+
+        let mut result = vec![];
+        for stripe in 0..words[0].len() {
+            let received: Vec<GF232> = words.iter().map(|w| w[stripe]).collect();
+            // M * phi_f = [S1 * phi_f; S2 * phi_f], and since S1 and S2 are symmetric,
+            // the contents of the failed node are phi_f^T S1 + lambda_f * phi_f^T S2
+            let m_phi = inverse.mul_vec(&received);
+            for l in 0..alpha {
+                result.push(m_phi[l] + lambda * m_phi[alpha + l]);
+            }
+        }
+        Ok(as_shard(&result))
+    }
+}
+
+/// Simulates replacing the failed node, both by regenerating it from `d` helpers and by
+/// decoding the data from `k` nodes and encoding it again, checking that both ways
+/// produce the original shard and counting the bytes downloaded in each case
+pub fn simulate_repair(msr: &Msr, data: &[u8], failed: usize) -> RepairStats {
+    let shards = msr.encode(data);
+    let others: Vec<usize> = (0..msr.n()).filter(|&idx| idx != failed).collect();
+
+    let sent: Vec<(usize, Vec<u8>)> = others[..msr.d()]
+        .iter()
+        .map(|&idx| (idx, msr.helper_data(&shards[idx], failed)))
+        .collect();
+    let helpers: Vec<(usize, &[u8])> = sent.iter().map(|&(idx, ref data)| (idx, &data[..])).collect();
+    let regenerated = msr.regenerate(failed, &helpers).unwrap();
+    assert_eq!(regenerated, shards[failed]);
+
+    let downloaded: Vec<(usize, &[u8])> = others[..msr.k()]
+        .iter()
+        .map(|&idx| (idx, &shards[idx][..]))
+        .collect();
+    let decoded = msr.decode(&downloaded).unwrap();
+    assert_eq!(msr.encode(&decoded)[failed], shards[failed]);
+
+    RepairStats {
+        regenerating: sent.iter().map(|(_, data)| data.len()).sum(),
+        naive: downloaded.iter().map(|&(_, shard)| shard.len()).sum(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::DecodeError;
+    use config::ConfigError;
+    use super::{simulate_repair, Msr};
+
+    #[test]
+    fn test_decode() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let msr = Msr::new(7, 4).unwrap();
+        let shards = msr.encode(&data);
+        assert_eq!(shards.len(), 7);
+        // any 4 of the 7 shards
+        for subset in 0..128u32 {
+            if subset.count_ones() != 4 {
+                continue;
+            }
+            let selected: Vec<(usize, &[u8])> = (0..7)
+                .filter(|&idx| subset & (1 << idx) != 0)
+                .map(|idx| (idx, &shards[idx][..]))
+                .collect();
+            assert_eq!(msr.decode(&selected), Ok(data.clone()));
+        }
+        let selected: Vec<(usize, &[u8])> = (0..3).map(|idx| (idx, &shards[idx][..])).collect();
+        assert_eq!(
+            msr.decode(&selected),
+            Err(DecodeError::NotEnoughShards { found: 3, required: 4 })
+        );
+    }
+
+    #[test]
+    fn test_regenerate() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        for &(n, k) in &[(3, 2), (5, 3), (7, 4), (10, 5)] {
+            let msr = Msr::new(n, k).unwrap();
+            for failed in 0..n {
+                let stats = simulate_repair(&msr, &data, failed);
+                // d / alpha = 2 shards' worth of data instead of k shards
+                assert_eq!(stats.regenerating * k, stats.naive * 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(Msr::new(3, 0), Err(ConfigError::NoDataShards));
+        assert!(Msr::new(3, 1).is_err());
+        assert!(Msr::new(6, 4).is_err());
+        assert!(Msr::new(7, 4).is_ok());
+    }
+}
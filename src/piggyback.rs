@@ -0,0 +1,308 @@
+use codec::{as_shard, as_words, frame, unframe, validate_shards, StripeDecoder, StripeEncoder};
+use codec::DecodeError;
+use config::{CodecConfig, ConfigError};
+use gf232::GF232;
+use std::collections::HashMap;
+
+/// Piggybacked Reed-Solomon code: every stripe consists of two substripes of `k` `u32`s,
+/// each of which is encoded with the usual Reed-Solomon code, and sums of the data of
+/// the first substripe are added onto the parities of the second one. The data shards
+/// are split into `n - k - 1` groups, and the parity shard `k + 1 + g` carries the
+/// piggyback of the group `g`; the parity shard `k` carries none.
+///
+/// A single lost data shard can then be repaired by reading the second substripes of
+/// `k` shards, which recovers the second substripe of the lost shard and the piggyback
+/// of its group, and the first substripes of the rest of the group - instead of both
+/// substripes of `k` shards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PiggybackRs {
+    n: usize,
+    k: usize,
+}
+
+/// One of the two substripes stored in every shard
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Substripe {
+    First,
+    Second,
+}
+
+/// Extracts the `u32`s of the given substripe from a shard created by
+/// `PiggybackRs::encode`
+pub fn substripe(shard: &[u8], half: Substripe) -> Vec<u8> {
+    let offset = match half {
+        Substripe::First => 0,
+        Substripe::Second => 4,
+    };
+    let mut result = vec![];
+    for pair in shard.chunks(8) {
+        result.extend(&pair[offset..offset + 4]);
+    }
+    result
+}
+
+impl PiggybackRs {
+    /// Creates a code with `n` shards, any `k` of which can recover the data. There has
+    /// to be at least one parity shard.
+    pub fn new(n: usize, k: usize) -> Result<PiggybackRs, ConfigError> {
+        CodecConfig::new(n, k)?;
+        if n == k {
+            return Err(ConfigError::Unsupported("there are no parity shards"));
+        }
+        Ok(PiggybackRs { n, k })
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The number of piggyback groups
+    fn groups(&self) -> usize {
+        self.n - self.k - 1
+    }
+
+    /// The indices of the data shards whose first substripes are piggybacked onto the
+    /// given shard
+    fn piggybacked(&self, idx: usize) -> Vec<usize> {
+        if idx <= self.k {
+            return vec![];
+        }
+        let group = idx - self.k - 1;
+        (0..self.k)
+            .filter(|&j| j * self.groups() / self.k == group)
+            .collect()
+    }
+
+    /// Calculates the piggyback added onto the given shard from the first substripe
+    fn piggyback(&self, idx: usize, first: &[u32]) -> u32 {
+        self.piggybacked(idx)
+            .into_iter()
+            .fold(0, |acc, j| acc ^ first[j])
+    }
+
+    /// Encodes the data into `n` shards, each of which stores the `u32`s of both
+    /// substripes of every stripe alternately. The length of the data is prepended to
+    /// it, and it is padded to a multiple of `2 * k` `u32`s.
+    pub fn encode(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        let stripe_size = 8 * self.k;
+        let stream = frame(data, stripe_size);
+
+        let encoder = StripeEncoder::new(self.n, self.k);
+        let mut result = vec![vec![]; self.n];
+        for stripe in stream.chunks(stripe_size) {
+            let words: Vec<u32> = as_words(stripe).into_iter().map(|x| x.0).collect();
+            let first = encoder.encode(&words[..self.k]);
+            let second = encoder.encode(&words[self.k..]);
+            for (idx, shard) in result.iter_mut().enumerate() {
+                shard.push(GF232(first[idx]));
+                shard.push(GF232(second[idx] ^ self.piggyback(idx, &words[..self.k])));
+            }
+        }
+        result.iter().map(|words| as_shard(words)).collect()
+    }
+
+    /// Decodes the data from any `k` shards: the first substripe is decoded as usual,
+    /// which allows the piggybacks to be removed from the second one
+    pub fn decode(&self, shards: &[(usize, &[u8])]) -> Result<Vec<u8>, DecodeError> {
+        let length = validate_shards(shards, self.n, self.k, 8)?;
+        let words: Vec<(usize, Vec<GF232>)> = shards[..self.k]
+            .iter()
+            .map(|&(idx, shard)| (idx, as_words(shard)))
+            .collect();
+        let indices: Vec<usize> = words.iter().map(|&(idx, _)| idx).collect();
+        let decoder = StripeDecoder::new(&indices, self.k);
+
+        let mut stream = vec![];
+        for pos in (0..length / 4).step_by(2) {
+            let first: Vec<(usize, u32)> = words
+                .iter()
+                .map(|&(idx, ref words)| (idx, words[pos].0))
+                .collect();
+            let first = decoder.decode(&first);
+            let second: Vec<(usize, u32)> = words
+                .iter()
+                .map(|&(idx, ref words)| (idx, words[pos + 1].0 ^ self.piggyback(idx, &first)))
+                .collect();
+            stream.extend(first.into_iter().chain(decoder.decode(&second)).map(GF232));
+        }
+        unframe(&as_shard(&stream))
+    }
+
+    /// Returns the substripes which have to be read in order to repair the shard `lost`,
+    /// assuming all the other shards are available
+    pub fn repair_plan(&self, lost: usize) -> Vec<(usize, Substripe)> {
+        let others: Vec<usize> = (0..self.k + 1).filter(|&idx| idx != lost).collect();
+        let parity = match (self.k + 1..self.n).find(|&idx| self.piggybacked(idx).contains(&lost)) {
+            Some(parity) => parity,
+            None => {
+                // no piggyback to use: read both substripes of `k` shards
+                return others
+                    .into_iter()
+                    .take(self.k)
+                    .flat_map(|idx| vec![(idx, Substripe::First), (idx, Substripe::Second)])
+                    .collect();
+            }
+        };
+        let mut result: Vec<(usize, Substripe)> = others
+            .into_iter()
+            .chain(Some(parity))
+            .map(|idx| (idx, Substripe::Second))
+            .collect();
+        for j in self.piggybacked(parity) {
+            if j != lost {
+                result.push((j, Substripe::First));
+            }
+        }
+        result
+    }
+
+    /// Repairs the shard `lost` from the substripes listed by `repair_plan`, extracted
+    /// with `substripe`
+    pub fn repair(
+        &self,
+        lost: usize,
+        pieces: &[(usize, Substripe, &[u8])],
+    ) -> Result<Vec<u8>, DecodeError> {
+        if lost >= self.n {
+            return Err(DecodeError::InvalidShardIndex(lost));
+        }
+        let plan = self.repair_plan(lost);
+        let pieces: HashMap<(usize, Substripe), &[u8]> = pieces
+            .iter()
+            .map(|&(idx, half, data)| ((idx, half), data))
+            .collect();
+        if plan.iter().any(|key| !pieces.contains_key(key)) {
+            return Err(DecodeError::NotEnoughShards {
+                found: pieces.len(),
+                required: plan.len(),
+            });
+        }
+        let length = pieces[&plan[0]].len();
+        if !length.is_multiple_of(4) || pieces.values().any(|data| data.len() != length) {
+            return Err(DecodeError::InvalidShardLength);
+        }
+        let words: HashMap<(usize, Substripe), Vec<GF232>> = pieces
+            .into_iter()
+            .map(|(key, data)| (key, as_words(data)))
+            .collect();
+        let word = |idx: usize, half: Substripe, pos: usize| words[&(idx, half)][pos].0;
+
+        let parity = plan.iter()
+            .find(|&&(idx, half)| half == Substripe::Second && idx > self.k)
+            .map(|&(idx, _)| idx);
+        let indices = |wanted: Substripe| -> Vec<usize> {
+            plan.iter()
+                .filter(|&&(idx, half)| half == wanted && Some(idx) != parity)
+                .map(|&(idx, _)| idx)
+                .collect()
+        };
+        let stripe = |wanted: Substripe, pos: usize| -> Vec<(usize, u32)> {
+            indices(wanted).into_iter().map(|idx| (idx, word(idx, wanted, pos))).collect()
+        };
+        let encoder = StripeEncoder::new(self.n, self.k);
+        let second_decoder = StripeDecoder::new(&indices(Substripe::Second), self.k);
+
+        let mut result = vec![];
+        match parity {
+            None => {
+                // both substripes of `k` shards without piggybacks
+                let first_decoder = StripeDecoder::new(&indices(Substripe::First), self.k);
+                for pos in 0..length / 4 {
+                    let first = first_decoder.decode(&stripe(Substripe::First, pos));
+                    let second = second_decoder.decode(&stripe(Substripe::Second, pos));
+                    result.push(GF232(encoder.encode(&first)[lost]));
+                    result.push(GF232(
+                        encoder.encode(&second)[lost] ^ self.piggyback(lost, &first),
+                    ));
+                }
+            }
+            Some(parity) => {
+                // the second substripe, and the piggyback of the group of the lost shard
+                for pos in 0..length / 4 {
+                    let second = second_decoder.decode(&stripe(Substripe::Second, pos));
+                    let decoded = encoder.encode(&second);
+                    let mut first = word(parity, Substripe::Second, pos) ^ decoded[parity];
+                    for &(idx, half) in &plan {
+                        if half == Substripe::First {
+                            first ^= word(idx, half, pos);
+                        }
+                    }
+                    result.push(GF232(first));
+                    result.push(GF232(decoded[lost]));
+                }
+            }
+        }
+        Ok(as_shard(&result))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::DecodeError;
+    use config::ConfigError;
+    use super::{substripe, PiggybackRs};
+
+    #[test]
+    fn test_decode() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let code = PiggybackRs::new(7, 4).unwrap();
+        let shards = code.encode(&data);
+        for subset in 0..128u32 {
+            if subset.count_ones() != 4 {
+                continue;
+            }
+            let selected: Vec<(usize, &[u8])> = (0..7)
+                .filter(|&idx| subset & (1 << idx) != 0)
+                .map(|idx| (idx, &shards[idx][..]))
+                .collect();
+            assert_eq!(code.decode(&selected), Ok(data.clone()));
+        }
+    }
+
+    #[test]
+    fn test_repair() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let code = PiggybackRs::new(14, 10).unwrap();
+        let shards = code.encode(&data);
+        for lost in 0..14 {
+            let plan = code.repair_plan(lost);
+            let pieces: Vec<_> = plan.iter()
+                .map(|&(idx, half)| (idx, half, substripe(&shards[idx], half)))
+                .collect();
+            let pieces: Vec<_> = pieces.iter()
+                .map(|&(idx, half, ref data)| (idx, half, &data[..]))
+                .collect();
+            assert_eq!(code.repair(lost, &pieces), Ok(shards[lost].clone()));
+            if lost < 10 {
+                // at most 14 substripes read instead of 20
+                assert!(plan.len() <= 14);
+            } else {
+                assert_eq!(plan.len(), 20);
+            }
+        }
+
+        // substripes which aren't made of whole `u32`s
+        let pieces: Vec<_> = code.repair_plan(0)
+            .into_iter()
+            .map(|(idx, half)| (idx, half, substripe(&shards[idx], half)))
+            .collect();
+        let pieces: Vec<_> = pieces.iter()
+            .map(|&(idx, half, ref data)| (idx, half, &data[..6]))
+            .collect();
+        assert_eq!(code.repair(0, &pieces), Err(DecodeError::InvalidShardLength));
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(PiggybackRs::new(4, 0), Err(ConfigError::NoDataShards));
+        assert_eq!(PiggybackRs::new(3, 4), Err(ConfigError::NotEnoughShards { n: 3, k: 4 }));
+        assert!(PiggybackRs::new(4, 4).is_err());
+    }
+}
@@ -20,6 +20,7 @@ impl BinaryPolynomial {
     }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl Add<BinaryPolynomial> for BinaryPolynomial {
     type Output = BinaryPolynomial;
 
@@ -34,6 +35,7 @@ impl AddAssign<BinaryPolynomial> for BinaryPolynomial {
     }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl Sub<BinaryPolynomial> for BinaryPolynomial {
     type Output = BinaryPolynomial;
 
@@ -133,9 +135,7 @@ impl fmt::Debug for BinaryPolynomial {
                     components.push("1".to_string());
                 }
             }
-            if degree > 0 {
-                degree -= 1;
-            }
+            degree = degree.saturating_sub(1);
             x <<= 1;
         }
         let result = if components.is_empty() {
@@ -153,21 +153,21 @@ mod test {
 
     #[test]
     fn test_add() {
-        let _1 = BinaryPolynomial(0b1);
+        let one = BinaryPolynomial(0b1);
         let _x = BinaryPolynomial(0b10);
-        assert_eq!(_1 + _1, BinaryPolynomial(0b0));
-        assert_eq!(_1 + _x, BinaryPolynomial(0b11));
+        assert_eq!(one + one, BinaryPolynomial(0b0));
+        assert_eq!(one + _x, BinaryPolynomial(0b11));
         assert_eq!(_x + _x, BinaryPolynomial(0b0));
     }
 
     #[test]
     fn test_mul() {
-        let _1 = BinaryPolynomial(0b1);
+        let one = BinaryPolynomial(0b1);
         let _x = BinaryPolynomial(0b10);
         let _x2 = BinaryPolynomial(0b100);
-        assert_eq!(_1 * _x, _x);
+        assert_eq!(one * _x, _x);
         assert_eq!(_x * _x, _x2);
-        assert_eq!((_x + _1) * _x, _x2 + _x);
+        assert_eq!((_x + one) * _x, _x2 + _x);
     }
 
     #[test]
@@ -182,14 +182,14 @@ mod test {
 
     #[test]
     fn test_debug() {
-        let _1 = BinaryPolynomial(0b1);
+        let one = BinaryPolynomial(0b1);
         let _x = BinaryPolynomial(0b10);
         let _x2 = BinaryPolynomial(0b100);
-        assert_eq!(format!("{:?}", _1), "1");
+        assert_eq!(format!("{:?}", one), "1");
         assert_eq!(format!("{:?}", _x), "x");
         assert_eq!(format!("{:?}", _x2), "x^2");
-        assert_eq!(format!("{:?}", _x + _1), "x + 1");
-        assert_eq!(format!("{:?}", _x2 + _1), "x^2 + 1");
+        assert_eq!(format!("{:?}", _x + one), "x + 1");
+        assert_eq!(format!("{:?}", _x2 + one), "x^2 + 1");
         assert_eq!(format!("{:?}", _x2 * _x), "x^3");
         assert_eq!(format!("{:?}", BinaryPolynomial(0)), "0");
     }
@@ -0,0 +1,146 @@
+use codec::{as_bytes, as_u64, as_words};
+use gf232::GF232;
+use sha256::{Hash, Sha256};
+use std::collections::HashSet;
+
+/// The secret key of the verifier in the proof-of-retrievability scheme: every `u32`
+/// `m_i` of a shard is tagged with `alpha * m_i + f(i)`, where `f` is a pseudorandom
+/// function keyed with `prf_key`. The tags are stored along with the shard, and a
+/// response to a challenge can only be consistent with them if it was calculated from
+/// the actual contents of the shard.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PorKey {
+    alpha: GF232,
+    prf_key: Hash,
+}
+
+/// A challenge asking for a combination of the `u32`s of a shard: every entry is the
+/// index of a stripe along with a coefficient
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Challenge {
+    pub entries: Vec<(usize, GF232)>,
+}
+
+/// The response of a prover to a challenge: the requested combination of the `u32`s of
+/// the shard, and the same combination of their tags
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Response {
+    pub mu: GF232,
+    pub sigma: GF232,
+}
+
+impl PorKey {
+    /// Creates a key from secret values, which should be chosen uniformly at random
+    pub fn new(alpha: GF232, prf_key: Hash) -> PorKey {
+        PorKey { alpha, prf_key }
+    }
+
+    /// The pseudorandom function masking the tag of the `u32` of the given shard
+    /// belonging to the given stripe
+    fn prf(&self, shard_index: usize, stripe: usize) -> GF232 {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.prf_key);
+        hasher.update(&as_bytes(shard_index as u64));
+        hasher.update(&as_bytes(stripe as u64));
+        as_words(&hasher.finalize()[..4])[0]
+    }
+
+    /// Calculates the tags of all the `u32`s of a shard created by `encode`, which are
+    /// to be stored along with the shard
+    pub fn tag_shard(&self, shard_index: usize, shard: &[u8]) -> Vec<GF232> {
+        as_words(shard)
+            .into_iter()
+            .enumerate()
+            .map(|(i, word)| self.alpha * word + self.prf(shard_index, i))
+            .collect()
+    }
+
+    /// Checks whether the response to the challenge is consistent with the contents of
+    /// the shard with the given index
+    pub fn verify(&self, shard_index: usize, challenge: &Challenge, response: &Response) -> bool {
+        let mut expected = self.alpha * response.mu;
+        for &(stripe, coefficient) in &challenge.entries {
+            expected += coefficient * self.prf(shard_index, stripe);
+        }
+        expected == response.sigma
+    }
+}
+
+impl Challenge {
+    /// Generates a challenge for `count` distinct stripes out of `stripes`, with nonzero
+    /// coefficients. The stripes and the coefficients are derived from the `seed`, which
+    /// should be chosen by the verifier unpredictably for every challenge.
+    pub fn generate(seed: &[u8], stripes: usize, count: usize) -> Challenge {
+        assert!(count <= stripes);
+        let mut entries = vec![];
+        let mut chosen = HashSet::new();
+        let mut counter = 0u64;
+        while entries.len() < count {
+            let mut hasher = Sha256::new();
+            hasher.update(seed);
+            hasher.update(&as_bytes(counter));
+            let hash = hasher.finalize();
+            counter += 1;
+            let stripe = (as_u64(&hash[..8]) % stripes as u64) as usize;
+            let coefficient = as_words(&hash[8..12])[0];
+            if coefficient != GF232(0) && chosen.insert(stripe) {
+                entries.push((stripe, coefficient));
+            }
+        }
+        Challenge { entries }
+    }
+}
+
+/// Calculates the response to a challenge from a shard created by `encode` and its tags
+pub fn prove(challenge: &Challenge, shard: &[u8], tags: &[GF232]) -> Response {
+    let mut mu = GF232(0);
+    let mut sigma = GF232(0);
+    for &(stripe, coefficient) in &challenge.entries {
+        mu += coefficient * as_words(&shard[4 * stripe..4 * stripe + 4])[0];
+        sigma += coefficient * tags[stripe];
+    }
+    Response { mu, sigma }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::encode;
+    use gf232::GF232;
+    use super::{prove, Challenge, PorKey};
+
+    #[test]
+    fn test_challenge_response() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(2000).collect();
+        let encoded = encode(&data, 6, 4);
+        let mut prf_key = [0; 32];
+        rng.fill_bytes(&mut prf_key);
+        let key = PorKey::new(GF232(rng.gen()), prf_key);
+        let tags = key.tag_shard(5, &encoded[5]);
+        let stripes = tags.len();
+
+        for round in 0..10u8 {
+            let challenge = Challenge::generate(&[round], stripes, 20);
+            assert_eq!(challenge.entries.len(), 20);
+            let response = prove(&challenge, &encoded[5], &tags);
+            assert!(key.verify(5, &challenge, &response));
+            // the response is bound to the shard index
+            assert!(!key.verify(4, &challenge, &response));
+
+            // a prover which lost a challenged part of the shard can't respond correctly
+            let mut damaged = encoded[5].clone();
+            let stripe = challenge.entries[round as usize].0;
+            damaged[4 * stripe + 1] ^= 0x20;
+            let response = prove(&challenge, &damaged, &tags);
+            assert!(!key.verify(5, &challenge, &response));
+        }
+
+        // a prover can't respond for a shard with another shard and its tags
+        let other_tags = key.tag_shard(4, &encoded[4]);
+        let challenge = Challenge::generate(b"seed", stripes, 5);
+        let response = prove(&challenge, &encoded[4], &other_tags);
+        assert!(key.verify(4, &challenge, &response));
+        assert!(!key.verify(5, &challenge, &response));
+    }
+}
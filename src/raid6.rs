@@ -0,0 +1,185 @@
+use codec::{self, as_shard, as_words, validate_shards, DecodeError};
+use gf232::GF232;
+
+/// The generator of the multiplicative group of the field used for the Q parity: `x + 1`
+/// (`x` itself isn't a generator modulo `IRR232`)
+const GENERATOR: GF232 = GF232(3);
+
+/// Returns `GENERATOR^j` for all `j < k`
+fn powers(k: usize) -> Vec<GF232> {
+    let mut result = vec![GF232(1)];
+    while result.len() < k {
+        let last = result[result.len() - 1];
+        result.push(last * GENERATOR);
+    }
+    result.truncate(k);
+    result
+}
+
+/// Calculates the P (XOR) and Q (sum of `g^j * D_j`) parities of the given data
+/// shards, with the missing ones treated as zeros
+fn parities(data: &[Option<Vec<GF232>>], words: usize) -> (Vec<GF232>, Vec<GF232>) {
+    let mut p = vec![GF232(0); words];
+    let mut q = vec![GF232(0); words];
+    for (shard, coefficient) in data.iter().zip(powers(data.len())) {
+        if let Some(ref shard) = *shard {
+            for (i, x) in shard.iter().enumerate() {
+                p[i] += *x;
+                q[i] += coefficient * *x;
+            }
+        }
+    }
+    (p, q)
+}
+
+/// Encodes the data into `k + 2` shards: the `k` data shards are the same as those
+/// created by `encode` (with the length prepended to the data), followed by the P
+/// shard, the XOR of the data shards, and the Q shard, the sum of the data shards
+/// multiplied by consecutive powers of a generator of the field
+pub fn encode_raid6(data: &[u8], k: usize) -> Vec<Vec<u8>> {
+    let mut result = codec::encode(data, k, k);
+    let words = result[0].len() / 4;
+    let data: Vec<_> = result.iter().map(|shard| Some(as_words(shard))).collect();
+    let (p, q) = parities(&data, words);
+    result.push(as_shard(&p));
+    result.push(as_shard(&q));
+    result
+}
+
+/// Reconstructs all `k + 2` shards created by `encode_raid6` from at least `k` of them,
+/// using the closed-form formulas for one or two missing shards
+pub fn reconstruct_raid6(shards: &[(usize, &[u8])], k: usize) -> Result<Vec<Vec<u8>>, DecodeError> {
+    let words = validate_shards(shards, k + 2, k, 4)? / 4;
+    let get = |idx: usize| {
+        shards
+            .iter()
+            .find(|&&(i, _)| i == idx)
+            .map(|&(_, shard)| as_words(shard))
+    };
+    let mut data: Vec<Option<Vec<GF232>>> = (0..k).map(get).collect();
+    let (p, q) = (get(k), get(k + 1));
+    let missing: Vec<usize> = (0..k).filter(|&j| data[j].is_none()).collect();
+    let g = powers(k);
+
+    // the parities of the available data shards
+    let (p_partial, q_partial) = parities(&data, words);
+    match (missing.len(), p, q) {
+        (0, _, _) => (),
+        (1, Some(p), _) => {
+            // D_x = P + the rest of the data
+            let x = missing[0];
+            data[x] = Some(p.iter().zip(&p_partial).map(|(a, b)| *a + *b).collect());
+        }
+        (1, None, Some(q)) => {
+            // D_x = (Q + the rest of Q) / g^x
+            let x = missing[0];
+            let factor = g[x].inverse();
+            data[x] = Some(
+                q.iter()
+                    .zip(&q_partial)
+                    .map(|(a, b)| (*a + *b) * factor)
+                    .collect(),
+            );
+        }
+        (2, Some(p), Some(q)) => {
+            // D_x + D_y = P_xy, g^x D_x + g^y D_y = Q_xy
+            let (x, y) = (missing[0], missing[1]);
+            let factor = (g[x] + g[y]).inverse();
+            let mut dx = vec![];
+            let mut dy = vec![];
+            for i in 0..words {
+                let pxy = p[i] + p_partial[i];
+                let qxy = q[i] + q_partial[i];
+                let value = (qxy + g[y] * pxy) * factor;
+                dx.push(value);
+                dy.push(pxy + value);
+            }
+            data[x] = Some(dx);
+            data[y] = Some(dy);
+        }
+        _ => unreachable!("at least k shards are available"),
+    }
+
+    let (p, q) = parities(&data, words);
+    let mut result: Vec<Vec<u8>> = data.into_iter().map(|shard| as_shard(&shard.unwrap())).collect();
+    result.push(as_shard(&p));
+    result.push(as_shard(&q));
+    Ok(result)
+}
+
+/// Decodes the data from at least `k` shards created by `encode_raid6`
+pub fn decode_raid6(shards: &[(usize, &[u8])], k: usize) -> Result<Vec<u8>, DecodeError> {
+    let all = reconstruct_raid6(shards, k)?;
+    let data: Vec<(usize, &[u8])> = all[..k]
+        .iter()
+        .enumerate()
+        .map(|(idx, shard)| (idx, &shard[..]))
+        .collect();
+    Ok(codec::decode(&data, k))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use codec::{as_shard, as_words, decode, encode, DecodeError};
+    use gf232::GF232;
+    use super::{decode_raid6, encode_raid6, reconstruct_raid6};
+
+    /// Calculates the P and Q parities of the data shards independently of `parities`:
+    /// P byte by byte, and Q using Horner's rule, `(..(D_{k-1} g + D_{k-2}) g + ..) + D_0`
+    fn expected_parities(data_shards: &[Vec<u8>]) -> (Vec<u8>, Vec<u8>) {
+        let mut p = vec![0; data_shards[0].len()];
+        for shard in data_shards {
+            for (x, y) in p.iter_mut().zip(shard) {
+                *x ^= *y;
+            }
+        }
+        let mut q = vec![GF232(0); data_shards[0].len() / 4];
+        for shard in data_shards.iter().rev() {
+            for (x, y) in q.iter_mut().zip(as_words(shard)) {
+                *x = *x * GF232(3) + y;
+            }
+        }
+        (p, as_shard(&q))
+    }
+
+    #[test]
+    fn test_recovery() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let k = 5;
+        let shards = encode_raid6(&data, k);
+        assert_eq!(shards.len(), k + 2);
+        // the data shards are the ones created by `encode`, so the general decoder
+        // recovers the data from them
+        assert_eq!(shards[..k], encode(&data, k + 2, k)[..k]);
+        let data_shards: Vec<(usize, &[u8])> = (0..k).map(|idx| (idx, &shards[idx][..])).collect();
+        assert_eq!(decode(&data_shards, k), data);
+        let (p, q) = expected_parities(&shards[..k]);
+        assert_eq!((&shards[k], &shards[k + 1]), (&p, &q));
+
+        for x in 0..k + 2 {
+            for y in x..k + 2 {
+                let available: Vec<(usize, &[u8])> = (0..k + 2)
+                    .filter(|&idx| idx != x && idx != y)
+                    .map(|idx| (idx, &shards[idx][..]))
+                    .collect();
+                let reconstructed = reconstruct_raid6(&available, k).unwrap();
+                assert_eq!(reconstructed, shards);
+                // the parities recalculated from the recovered data shards
+                assert_eq!(
+                    (&reconstructed[k], &reconstructed[k + 1]),
+                    (&p, &q),
+                    "lost shards {} and {}",
+                    x,
+                    y
+                );
+                assert_eq!(decode_raid6(&available, k), Ok(data.clone()));
+            }
+        }
+        assert_eq!(
+            reconstruct_raid6(&[], 0),
+            Err(DecodeError::NotEnoughShards { found: 0, required: 1 })
+        );
+    }
+}
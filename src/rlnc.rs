@@ -0,0 +1,284 @@
+use codec::{as_shard, as_words, data_shards, join_data_shards, DecodeError};
+use gf232::GF232;
+use lt::SplitMix64;
+
+/// A coded packet: a linear combination of the `k` source symbols (the data shards
+/// created by `encode`) along with its coefficients
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Packet {
+    pub coefficients: Vec<GF232>,
+    pub payload: Vec<GF232>,
+}
+
+impl Packet {
+    /// Serializes the packet: the header with the `k` coefficients is followed by the
+    /// payload, all as little-endian `u32`s
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = as_shard(&self.coefficients);
+        result.extend(as_shard(&self.payload));
+        result
+    }
+
+    /// Deserializes a packet of a generation of `k` source symbols
+    pub fn from_bytes(bytes: &[u8], k: usize) -> Option<Packet> {
+        if bytes.len() < 4 * k || !bytes.len().is_multiple_of(4) {
+            return None;
+        }
+        Some(Packet {
+            coefficients: as_words(&bytes[..4 * k]),
+            payload: as_words(&bytes[4 * k..]),
+        })
+    }
+
+    /// Returns `self + factor * other`
+    fn add_scaled(&mut self, other: &Packet, factor: GF232) {
+        for (x, y) in self.coefficients.iter_mut().zip(&other.coefficients) {
+            *x += factor * *y;
+        }
+        for (x, y) in self.payload.iter_mut().zip(&other.payload) {
+            *x += factor * *y;
+        }
+    }
+
+    fn scale(&mut self, factor: GF232) {
+        for x in self.coefficients.iter_mut().chain(self.payload.iter_mut()) {
+            *x *= factor;
+        }
+    }
+}
+
+/// Returns a random linear combination of the packets, with nonzero coefficients
+fn combine(packets: &[Packet], rng: &mut SplitMix64) -> Packet {
+    let mut result = Packet {
+        coefficients: vec![GF232(0); packets[0].coefficients.len()],
+        payload: vec![GF232(0); packets[0].payload.len()],
+    };
+    for packet in packets {
+        let factor = GF232((rng.next() as u32).max(1));
+        result.add_scaled(packet, factor);
+    }
+    result
+}
+
+/// The source of a generation, emitting random linear combinations of its `k` source
+/// symbols
+pub struct Encoder {
+    source: Vec<Packet>,
+    rng: SplitMix64,
+}
+
+impl Encoder {
+    /// Creates an encoder for the data, split into `k` source symbols. The seed
+    /// initialises the generator of the coefficients.
+    pub fn new(data: &[u8], k: usize, seed: u64) -> Encoder {
+        let source = data_shards(data, k)
+            .into_iter()
+            .enumerate()
+            .map(|(j, shard)| {
+                let mut coefficients = vec![GF232(0); k];
+                coefficients[j] = GF232(1);
+                Packet {
+                    coefficients,
+                    payload: as_words(&shard),
+                }
+            })
+            .collect();
+        Encoder {
+            source,
+            rng: SplitMix64(seed),
+        }
+    }
+
+    /// Returns a new random linear combination of the source symbols
+    pub fn packet(&mut self) -> Packet {
+        combine(&self.source, &mut self.rng)
+    }
+}
+
+/// Decoder collecting packets and keeping them in reduced row echelon form, so that
+/// every packet is eliminated against the previous ones as it arrives
+pub struct Decoder {
+    k: usize,
+    /// The innovative packets received so far, with the column of the leading
+    /// coefficient of each of them, which is 1 and which is 0 in all the other rows
+    rows: Vec<(usize, Packet)>,
+}
+
+impl Decoder {
+    pub fn new(k: usize) -> Decoder {
+        Decoder { k, rows: vec![] }
+    }
+
+    /// The number of linearly independent packets received so far
+    pub fn rank(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.rank() == self.k
+    }
+
+    /// Adds a received packet. Returns whether it was innovative, i.e. increased the
+    /// rank. Packets with a payload of a different length than the first innovative
+    /// packet are rejected.
+    pub fn add(&mut self, packet: &Packet) -> bool {
+        if packet.coefficients.len() != self.k {
+            return false;
+        }
+        if let Some((_, first)) = self.rows.first() {
+            if packet.payload.len() != first.payload.len() {
+                return false;
+            }
+        }
+        let mut packet = packet.clone();
+        for &(pivot, ref row) in &self.rows {
+            let factor = packet.coefficients[pivot];
+            if factor != GF232(0) {
+                packet.add_scaled(row, factor);
+            }
+        }
+        let pivot = match packet.coefficients.iter().position(|x| *x != GF232(0)) {
+            Some(pivot) => pivot,
+            None => return false,
+        };
+        let factor = packet.coefficients[pivot].inverse();
+        packet.scale(factor);
+        for &mut (_, ref mut row) in &mut self.rows {
+            let factor = row.coefficients[pivot];
+            if factor != GF232(0) {
+                row.add_scaled(&packet, factor);
+            }
+        }
+        self.rows.push((pivot, packet));
+        true
+    }
+
+    /// Decodes the data once `k` innovative packets have been received
+    pub fn decode(&self) -> Result<Vec<u8>, DecodeError> {
+        if !self.is_complete() {
+            return Err(DecodeError::NotEnoughShards {
+                found: self.rank(),
+                required: self.k,
+            });
+        }
+        let mut rows: Vec<&(usize, Packet)> = self.rows.iter().collect();
+        rows.sort_by_key(|&&(pivot, _)| pivot);
+        let source: Vec<Vec<u8>> = rows.iter().map(|(_, row)| as_shard(&row.payload)).collect();
+        join_data_shards(&source)
+    }
+}
+
+/// A relay forwarding random linear combinations of the packets it has received,
+/// without having to decode them
+pub struct Recoder {
+    /// The received packets, reduced to a basis of the space they span
+    basis: Decoder,
+    rng: SplitMix64,
+}
+
+impl Recoder {
+    pub fn new(k: usize, seed: u64) -> Recoder {
+        Recoder {
+            basis: Decoder::new(k),
+            rng: SplitMix64(seed),
+        }
+    }
+
+    /// Adds a received packet. Returns whether it was innovative.
+    pub fn add(&mut self, packet: &Packet) -> bool {
+        self.basis.add(packet)
+    }
+
+    /// The rank of the space spanned by the received packets
+    pub fn rank(&self) -> usize {
+        self.basis.rank()
+    }
+
+    /// Returns a random linear combination of the received packets, or `None` if there
+    /// are none yet
+    pub fn packet(&mut self) -> Option<Packet> {
+        if self.basis.rows.is_empty() {
+            return None;
+        }
+        let rows: Vec<Packet> = self.basis.rows.iter().map(|(_, row)| row.clone()).collect();
+        Some(combine(&rows, &mut self.rng))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{self, Rng};
+    use gf232::GF232;
+    use super::{Decoder, Encoder, Packet, Recoder};
+
+    #[test]
+    fn test_lossy_relays() {
+        let mut rng = rand::thread_rng();
+        let data: Vec<u8> = rng.gen_iter().take(3000).collect();
+        let k = 16;
+        let mut encoder = Encoder::new(&data, k, rng.gen());
+        let mut relays = [Recoder::new(k, rng.gen()), Recoder::new(k, rng.gen())];
+        let mut decoder = Decoder::new(k);
+
+        // every relay hears only the packets of the source with some ids, and every link
+        // loses a third of the packets - including the first one, so that the relays
+        // together never get all `k` dimensions
+        for id in 0..k {
+            let packet = encoder.packet();
+            let relay = &mut relays[id % 2];
+            if id == 0 || rng.gen_weighted_bool(3) {
+                continue;
+            }
+            let bytes = packet.to_bytes();
+            assert!(relay.add(&Packet::from_bytes(&bytes, k).unwrap()));
+        }
+        assert!(relays.iter().all(|relay| relay.rank() < k));
+
+        // the decoder gets the recoded packets of both relays
+        let mut sent = 0;
+        let mut rank = 0;
+        let total: usize = relays.iter().map(|relay| relay.rank()).sum();
+        assert!(total < k);
+        while decoder.rank() < total {
+            let packet = relays[sent % 2].packet();
+            sent += 1;
+            let packet = match packet {
+                Some(ref packet) if !rng.gen_weighted_bool(3) => packet,
+                _ => continue,
+            };
+            let innovative = decoder.add(&Packet::from_bytes(&packet.to_bytes(), k).unwrap());
+            assert_eq!(decoder.rank(), rank + innovative as usize);
+            rank = decoder.rank();
+            assert!(sent < 100 * k);
+        }
+        assert!(decoder.decode().is_err());
+
+        // the rest comes directly from the source
+        while !decoder.is_complete() {
+            decoder.add(&encoder.packet());
+        }
+        assert_eq!(decoder.decode(), Ok(data));
+    }
+
+    #[test]
+    fn test_payload_length() {
+        let data: Vec<u8> = (0..100).collect();
+        let k = 4;
+        let mut encoder = Encoder::new(&data, k, 1);
+        let mut decoder = Decoder::new(k);
+        assert!(decoder.add(&encoder.packet()));
+
+        let mut truncated = encoder.packet();
+        truncated.payload.pop();
+        assert!(!decoder.add(&truncated));
+        let mut extended = encoder.packet();
+        extended.payload.push(GF232(1));
+        assert!(!decoder.add(&extended));
+        assert_eq!(decoder.rank(), 1);
+
+        while !decoder.is_complete() {
+            decoder.add(&encoder.packet());
+        }
+        assert_eq!(decoder.decode(), Ok(data));
+    }
+}